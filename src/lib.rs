@@ -122,11 +122,14 @@
 //!                 &json!({"category" : "fiction","author" : "Herman Melville","title" : "Moby Dick","isbn" : "0-553-21311-3","price" : 8.99})
 //!             ]);
 //! ```
+extern crate caseless;
 extern crate core;
 #[macro_use]
 extern crate log;
+extern crate regex;
 extern crate serde;
 extern crate serde_json;
+extern crate unicode_normalization;
 
 use serde_json::Value;
 
@@ -143,8 +146,8 @@ note = "It will be move to common module. since 0.5"
 )]
 pub use select::JsonPathError;
 
-pub use selector::{JsonSelector, JsonSelectorMut};
-pub use paths::PathParser;
+pub use selector::{DescentOrder, JsonSelector, JsonSelectorMut, KeyMatch};
+pub use paths::{FunctionArgument, FunctionRegistry, FunctionType, ParserMode, PathParser};
 use std::rc::Rc;
 
 #[doc(hidden)]
@@ -304,7 +307,7 @@ pub fn selector<'a>(json: &'a Value) -> impl FnMut(&'a str) -> Result<Vec<&'a Va
 /// assert_eq!(json, ret);
 /// ```
 pub fn selector_as<'a, T: serde::de::DeserializeOwned>(json: &'a Value)
-                                                   -> impl FnMut(&'a str) -> Result<Vec<T>, JsonPathError> + '_
+                                                   -> impl FnMut(&'a str) -> Result<Vec<T>, JsonPathError> + 'a
 {
     let mut selector = JsonSelector::default();
     let _ = selector.value(json);
@@ -370,8 +373,7 @@ pub fn select<'a>(json: &'a Value, path: &'a str) -> Result<Vec<&'a Value>, Json
 pub fn select_as_str(json_str: &str, path: &str) -> Result<String, JsonPathError> {
     let json = serde_json::from_str(json_str).map_err(|e| JsonPathError::Serde(e.to_string()))?;
     let parser = PathParser::compile(path).map_err(|e| JsonPathError::from(&e))?;
-    let ret = JsonSelector::new(parser).value(&json).select()?;
-    serde_json::to_string(&ret).map_err(|e| JsonPathError::Serde(e.to_string()))
+    JsonSelector::new(parser).value(&json).select_as_str()
 }
 
 /// It is the same to `select` function but it deserialize the the result as given type `T`.
@@ -634,7 +636,7 @@ impl<'a> PathCompiled<'a> {
     /// Compile a path expression and return a compiled instance.
     ///
     /// If parsing the path fails, it will return an error.
-    pub fn compile(path: &str) -> Result<PathCompiled, JsonPathError> {
+    pub fn compile(path: &str) -> Result<PathCompiled<'_>, JsonPathError> {
         let parser = PathParser::compile(path).map_err(|e| JsonPathError::from(&e))?;
         Ok(PathCompiled {
             parser: Rc::new(parser)
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use JsonPathError;
+use super::tokens::{FunctionToken, FunctionType};
+
+/// An argument of a registered function, of the type its parameter declares.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FunctionArgument<'a> {
+    /// A `FunctionType::Value` argument, `None` when it has no value.
+    Value(Option<&'a Value>),
+    /// A `FunctionType::Nodes` argument.
+    Nodes(Vec<&'a Value>),
+    /// A `FunctionType::Logical` argument.
+    Logical(bool),
+}
+
+type ValueFunction = dyn Fn(&[FunctionArgument]) -> Option<Value>;
+type LogicalFunction = dyn Fn(&[FunctionArgument]) -> bool;
+
+#[derive(Clone)]
+pub(crate) enum FunctionBody {
+    Value(Rc<ValueFunction>),
+    Logical(Rc<LogicalFunction>),
+}
+
+#[derive(Clone)]
+pub(crate) struct CustomFunction {
+    parameters: Vec<FunctionType>,
+    body: FunctionBody,
+}
+
+impl CustomFunction {
+    pub fn parameters(&self) -> &[FunctionType] {
+        &self.parameters
+    }
+
+    pub fn result(&self) -> FunctionType {
+        match self.body {
+            FunctionBody::Value(_) => FunctionType::Value,
+            FunctionBody::Logical(_) => FunctionType::Logical,
+        }
+    }
+
+    pub fn body(&self) -> &FunctionBody {
+        &self.body
+    }
+}
+
+/// Functions a `PathParser` can call in filters besides the RFC 9535 ones,
+/// e.g. `$.items[?is_valid_sku(@.sku)]`.
+///
+/// Each function is registered with the types of its parameters, and the parser
+/// checks the arguments of a call against them as it does for `length` or `match`
+/// (RFC 9535 2.4.3). A `FunctionType::Value` function can be compared, and a
+/// `FunctionType::Logical` one is a test by itself.
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, CustomFunction>,
+}
+
+impl FunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a function that computes a value, or nothing.
+    pub fn register_value<F>(&mut self, name: &str, parameters: &[FunctionType], fun: F) -> Result<&mut Self, JsonPathError>
+        where
+            F: Fn(&[FunctionArgument]) -> Option<Value> + 'static
+    {
+        self.register(name, parameters, FunctionBody::Value(Rc::new(fun)))
+    }
+
+    /// Registers a function that tests its arguments.
+    pub fn register_logical<F>(&mut self, name: &str, parameters: &[FunctionType], fun: F) -> Result<&mut Self, JsonPathError>
+        where
+            F: Fn(&[FunctionArgument]) -> bool + 'static
+    {
+        self.register(name, parameters, FunctionBody::Logical(Rc::new(fun)))
+    }
+
+    // RFC 9535 2.4: a lowercase letter, then lowercase letters, digits or `_`
+    fn register(&mut self, name: &str, parameters: &[FunctionType], body: FunctionBody) -> Result<&mut Self, JsonPathError> {
+        let is_name = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !is_name {
+            return Err(JsonPathError::Path(format!("invalid function name: {}", name)));
+        }
+        if FunctionToken::from_name(name).is_some() {
+            return Err(JsonPathError::Path(format!("a standard function can not be replaced: {}", name)));
+        }
+
+        self.functions.insert(name.to_string(), CustomFunction { parameters: parameters.to_vec(), body });
+        Ok(self)
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&CustomFunction> {
+        self.functions.get(name)
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.functions.keys()).finish()
+    }
+}
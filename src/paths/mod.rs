@@ -1,9 +1,12 @@
 pub use self::parser_node_visitor::ParserNodeVisitor;
+pub use self::function_registry::{FunctionArgument, FunctionRegistry};
 pub use self::parser_token_handler::ParserTokenHandler;
-pub use self::path_parser::PathParser;
+pub use self::path_parser::{ParserMode, PathParser};
 pub use self::str_reader::StrRange;
-pub use self::tokenizer::TokenError;
+pub use self::tokenizer::{unescape, TokenError};
+pub use self::tokens::FunctionType;
 
+pub(crate) mod function_registry;
 mod str_reader;
 mod tokenizer;
 pub mod tokens;
@@ -15,54 +15,87 @@ pub trait ParserNodeVisitor<'a> {
             | ParseToken::All
             | ParseToken::Key(_)
             | ParseToken::Keys(_)
+            | ParseToken::KeyPattern(_)
             | ParseToken::Range(_, _, _)
             | ParseToken::Union(_)
             | ParseToken::Number(_)
-            | ParseToken::Bool(_) => {
+            | ParseToken::Bool(_)
+            | ParseToken::Null
+            | ParseToken::Regex(_)
+            | ParseToken::Literal(_)
+            | ParseToken::Context(_)
+            | ParseToken::Parameter(_) => {
                 token_handler.handle(&parse_node.token, parse_value_reader);
             }
-            ParseToken::In | ParseToken::Leaves => {
+            ParseToken::In
+            | ParseToken::Leaves
+            | ParseToken::PropertyName
+            | ParseToken::Parent
+            | ParseToken::Aggregate(_) => {
                 if let Some(n) = &parse_node.left {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 token_handler.handle(&parse_node.token, parse_value_reader);
 
                 if let Some(n) = &parse_node.right {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
             }
             ParseToken::Array => {
                 if let Some(n) = &parse_node.left {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 token_handler.handle(&parse_node.token, parse_value_reader);
 
                 if let Some(n) = &parse_node.right {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 token_handler.handle(&ParseToken::ArrayEof, parse_value_reader);
             }
+            ParseToken::Selectors => {
+                token_handler.handle(&parse_node.token, parse_value_reader);
+
+                // each selector of the union is visited as a bracket of its own
+                let mut node = Some(parse_node);
+                while let Some(n) = node {
+                    if let Some(selector) = &n.left {
+                        token_handler.handle(&ParseToken::Array, parse_value_reader);
+                        self.visit(selector, token_handler, parse_value_reader);
+                        token_handler.handle(&ParseToken::ArrayEof, parse_value_reader);
+                    }
+                    node = n.right.as_deref();
+                }
+            }
             ParseToken::Filter(FilterToken::And) | ParseToken::Filter(FilterToken::Or) => {
                 if let Some(n) = &parse_node.left {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 if let Some(n) = &parse_node.right {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 token_handler.handle(&parse_node.token, parse_value_reader);
             }
-            ParseToken::Filter(_) => {
+            ParseToken::Argument => {
+                if let Some(n) = &parse_node.left {
+                    self.visit(n, token_handler, parse_value_reader);
+                }
+
+                if let Some(n) = &parse_node.right {
+                    self.visit(n, token_handler, parse_value_reader);
+                }
+            }
+            ParseToken::Filter(_) | ParseToken::Function(_) | ParseToken::Arithmetic(_) => {
                 if let Some(n) = &parse_node.left {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 if let Some(n) = &parse_node.right {
-                    self.visit(&*n, token_handler, parse_value_reader);
+                    self.visit(n, token_handler, parse_value_reader);
                 }
 
                 token_handler.handle(&parse_node.token, parse_value_reader);
@@ -1,21 +1,94 @@
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::str::FromStr;
 
+use regex::{self, Regex, RegexBuilder};
+use serde_json::{Map, Number, Value};
+
+use selector::utils;
+use super::function_registry::{CustomFunction, FunctionRegistry};
 use super::parser_token_handler::ParserTokenHandler;
-use super::parser_node_visitor::ParserNodeVisitor;
+use super::ParserNodeVisitor;
 use super::str_reader::StrRange;
-use super::tokenizer::{TokenError, TokenReader};
-use super::tokens::{FilterToken, ParseToken, Token};
+use super::tokenizer::{find_invalid_name_char, is_blank, unescape, TokenError, TokenReader};
+use super::tokens::{AggregateToken, ArithmeticToken, ContextToken, FilterToken, FunctionToken, FunctionType, ParseToken, Token};
+
+/// Grammar and evaluation rules a `PathParser` is compiled with.
+///
+/// `Default` keeps the historical behaviour of this crate. `Rfc9535` follows
+/// [RFC 9535](https://www.rfc-editor.org/rfc/rfc9535): the stricter grammar is
+/// checked at compile time, and a `JsonSelector` running the compiled path
+/// keeps duplicate nodes, returns them in document order and applies the
+/// standard's comparison rules in filters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParserMode {
+    #[default]
+    Default,
+    Rfc9535,
+}
 
 #[derive(Clone, Debug)]
 pub struct PathParser<'a> {
     parser: ParserImpl<'a>,
+    max_descent_depth: Option<usize>,
 }
 
 impl<'a> PathParser<'a> {
     pub fn compile(input: &'a str) -> Result<Self, TokenError> {
-        let mut parser = ParserImpl::new(input);
+        Self::compile_with_mode(input, ParserMode::Default)
+    }
+
+    pub fn compile_with_mode(input: &'a str, mode: ParserMode) -> Result<Self, TokenError> {
+        Self::compile_with_functions(input, mode, Rc::default())
+    }
+
+    /// Compiles a path that may call the functions of `functions` in its filters.
+    /// A call of an unknown function, or with arguments of the wrong types, is an error.
+    pub fn compile_with_functions(input: &'a str, mode: ParserMode, functions: Rc<FunctionRegistry>) -> Result<Self, TokenError> {
+        let mut parser = ParserImpl::new(input, mode, functions);
         parser.compile()?;
-        Ok(PathParser { parser })
+        Ok(PathParser { parser, max_descent_depth: None })
+    }
+
+    /// Bounds how many levels `..` descends below the node it starts from:
+    /// with a depth of 1 `$..a` is `$.a`, with 2 it is `$.a` and `$.*.a`.
+    pub fn with_max_descent_depth(mut self, depth: usize) -> Self {
+        self.max_descent_depth = Some(depth);
+        self
+    }
+
+    pub fn mode(&self) -> ParserMode {
+        self.parser.mode
+    }
+
+    pub fn max_descent_depth(&self) -> Option<usize> {
+        self.max_descent_depth
+    }
+
+    /// The names of the `$name` parameters of this path, in the order they first appear.
+    pub fn parameters(&self) -> &[&'a str] {
+        &self.parser.parameters
+    }
+
+    /// Whether the path selects a parent with `^`, so the selector must keep track of them.
+    pub(crate) fn has_parent(&self) -> bool {
+        self.parser.has_parent
+    }
+
+    /// Whether a filter of the path uses `@property`, `@parentProperty` or `@path`,
+    /// so the filters of the path select among the members of an object.
+    pub(crate) fn has_context(&self) -> bool {
+        self.parser.has_context
+    }
+
+    /// The compiled regex of a `/pattern/flags` literal or a glob member name of this path.
+    pub(crate) fn regex(&self, literal: &str) -> Option<Rc<Regex>> {
+        self.parser.regexes.get(literal).cloned()
+    }
+
+    /// A function of the `FunctionRegistry` this path was compiled with.
+    pub(crate) fn function(&self, name: &str) -> Option<&CustomFunction> {
+        self.parser.functions.get(name)
     }
 
     pub(crate) fn parse<F>(&self, parse_token_handler: &mut F) -> Result<(), String>
@@ -43,28 +116,50 @@ impl<'a> ParserNodeVisitor<'a> for PathParser<'a> {}
 struct ParserImpl<'a> {
     token_reader: TokenReader<'a>,
     parse_node: Option<ParserNode>,
+    mode: ParserMode,
+    regexes: HashMap<&'a str, Rc<Regex>>,
+    functions: Rc<FunctionRegistry>,
+    parameters: Vec<&'a str>,
+    filter_depth: usize,
+    has_parent: bool,
+    has_context: bool,
 }
 
 impl<'a> ParserImpl<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, mode: ParserMode, functions: Rc<FunctionRegistry>) -> Self {
         ParserImpl {
             token_reader: TokenReader::new(input),
             parse_node: None,
+            mode,
+            regexes: HashMap::new(),
+            functions,
+            parameters: Vec::new(),
+            filter_depth: 0,
+            has_parent: false,
+            has_context: false,
         }
     }
 
-    fn string_to_num<F, S: FromStr>(string: &str, msg_handler: F) -> Result<S, TokenError>
-        where
-            F: Fn() -> TokenError,
-    {
-        match string.parse() {
-            Ok(n) => Ok(n),
-            _ => Err(msg_handler()),
-        }
+    fn is_rfc(&self) -> bool {
+        self.mode == ParserMode::Rfc9535
     }
 
     pub fn compile(&mut self) -> Result<&mut Self, TokenError> {
-        self.parse_node = Some(self.json_path()?);
+        let node = self.json_path()?;
+
+        if self.is_rfc() {
+            // RFC 9535 does not allow blank space around the query,
+            // and the whole input must be consumed.
+            let input = self.token_reader.origin_str();
+            if input.ends_with(is_blank) {
+                return Err(TokenError::Position(input.trim_end_matches(is_blank).len()));
+            }
+            if self.token_reader.peek_token().is_ok() {
+                return Err(self.token_reader.to_peeked_error());
+            }
+        }
+
+        self.parse_node = Some(node);
         Ok(self)
     }
 
@@ -81,6 +176,9 @@ impl<'a> ParserImpl<'a> {
 
     fn paths(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
         debug!("#paths");
+        if self.is_rfc() {
+            self.eat_whitespace();
+        }
         match self.token_reader.peek_token() {
             Ok(Token::Dot(_)) => {
                 self.eat_token();
@@ -92,13 +190,75 @@ impl<'a> ParserImpl<'a> {
                 let node = self.array(prev)?;
                 self.paths(node)
             }
+            Ok(Token::Caret(_)) if self.mode == ParserMode::Default => {
+                self.eat_token();
+                self.has_parent = true;
+                let node = ParserNode {
+                    token: ParseToken::Parent,
+                    left: Some(Box::new(prev)),
+                    right: None,
+                };
+                match self.token_reader.peek_token() {
+                    Ok(Token::Dot(_)) | Ok(Token::OpenArray(_)) | Ok(Token::Caret(_)) | Ok(Token::Tilde(_)) => self.paths(node),
+                    _ => {
+                        self.path_end()?;
+                        Ok(node)
+                    }
+                }
+            }
+            Ok(Token::Tilde(_)) if self.mode == ParserMode::Default => {
+                self.eat_token();
+                self.path_end()?;
+                Ok(ParserNode {
+                    token: ParseToken::PropertyName,
+                    left: Some(Box::new(prev)),
+                    right: None,
+                })
+            }
+            // a brace only starts a JSON literal of a filter, so `$.p{q}` is not the member `p{q}`
+            Ok(Token::OpenBrace(_)) | Ok(Token::CloseBrace(_)) => Err(self.token_reader.to_peeked_error()),
             _ => Ok(prev),
         }
     }
 
+    //
+    // Nothing may follow a path that ends in `~` or `^`, so `$.a~.b` or `$.x^y` is an
+    // error rather than `$.a~` or `$.x^`. In a filter the expression goes on after the
+    // path instead.
+    //
+    fn path_end(&mut self) -> Result<(), TokenError> {
+        if self.filter_depth == 0 {
+            self.eat_whitespace();
+        }
+        match self.token_reader.peek_token() {
+            Err(TokenError::Eof) => Ok(()),
+            Ok(Token::Key(_))
+            | Ok(Token::Dot(_))
+            | Ok(Token::OpenArray(_))
+            | Ok(Token::Asterisk(_))
+            | Ok(Token::DoubleQuoted(_))
+            | Ok(Token::SingleQuoted(_))
+            | Ok(Token::At(_))
+            | Ok(Token::Absolute(_))
+            | Ok(Token::Caret(_))
+            | Ok(Token::Tilde(_)) => Err(self.token_reader.to_peeked_error()),
+            _ if self.filter_depth > 0 => Ok(()),
+            _ => Err(self.token_reader.to_peeked_error()),
+        }
+    }
+
     fn paths_dot(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
         debug!("#paths_dot");
         let node = self.path(prev)?;
+        if let ParseToken::Aggregate(_) = node.token {
+            // an aggregate function ends the path
+            return match self.token_reader.peek_token() {
+                Ok(Token::Dot(_)) | Ok(Token::OpenArray(_)) | Ok(Token::Caret(_)) | Ok(Token::Tilde(_)) => {
+                    Err(self.token_reader.to_peeked_error())
+                }
+                _ => Ok(node),
+            };
+        }
         self.paths(node)
     }
 
@@ -108,7 +268,7 @@ impl<'a> ParserImpl<'a> {
             Ok(Token::Dot(_)) => self.path_leaves(prev),
             Ok(Token::Asterisk(_)) => self.path_in_all(prev),
             Ok(Token::Key(_)) => self.path_in_key(prev),
-            Ok(Token::OpenArray(_)) => {
+            Ok(Token::OpenArray(_)) if self.mode == ParserMode::Default => {
                 self.eat_token();
                 self.array(prev)
             }
@@ -162,25 +322,72 @@ impl<'a> ParserImpl<'a> {
         })
     }
 
-    #[allow(clippy::unnecessary_wraps)]
     fn path_in_key(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
         debug!("#path_in_key");
+        let key = self.key()?;
+        if self.mode == ParserMode::Default {
+            if let (Ok(Token::OpenParenthesis(_)), ParseToken::Key(s)) = (self.token_reader.peek_token(), &key.token) {
+                let s = s.clone();
+                return self.aggregate(prev, s);
+            }
+        }
         Ok(ParserNode {
             token: ParseToken::In,
             left: Some(Box::new(prev)),
-            right: Some(Box::new(self.key()?)),
+            right: Some(Box::new(key)),
         })
     }
 
+    // `.sum()` and the like, with no arguments
+    fn aggregate(&mut self, prev: ParserNode, name: StrRange) -> Result<ParserNode, TokenError> {
+        debug!("#aggregate");
+        let aggregate = AggregateToken::from_name(self.token_reader.read_value(&name))
+            .ok_or(TokenError::Position(name.pos))?;
+        self.eat_token();
+        self.eat_whitespace();
+
+        let node = ParserNode {
+            token: ParseToken::Aggregate(aggregate),
+            left: Some(Box::new(prev)),
+            right: None,
+        };
+        self.close_token(node, Token::CloseParenthesis(StrRange::new(0, 0)))
+    }
+
     fn key(&mut self) -> Result<ParserNode, TokenError> {
         debug!("#key");
         match self.token_reader.next_token() {
-            Ok(Token::Key(s)) => Ok(self.create_node(ParseToken::Key(s))),
+            Ok(Token::Key(s)) => {
+                let name = self.token_reader.read_value(&s);
+                let invalid = if self.is_rfc() {
+                    find_invalid_name_char(name)
+                } else if self.filter_depth > 0 {
+                    find_arith_op(name)
+                } else {
+                    None
+                };
+                if let Some(offset) = invalid {
+                    return Err(TokenError::Position(s.pos + offset));
+                }
+                Ok(self.create_node(ParseToken::Key(s)))
+            }
             _ => Err(self.token_reader.to_error()),
         }
     }
 
-    fn boolean(&mut self) -> Result<ParserNode, TokenError> {
+    // any JSON number of a whole value, e.g. `1e2`, though only an int in RFC 9535
+    fn index(&self, s: &StrRange) -> Result<isize, TokenError> {
+        let val = self.token_reader.read_value(s);
+        if self.is_rfc() && !is_rfc_index(val) {
+            return Err(TokenError::Position(s.pos));
+        }
+        val.parse()
+            .ok()
+            .or_else(|| Number::from_str(val).ok().as_ref().and_then(utils::to_i64).map(|i| i as isize))
+            .ok_or(TokenError::Position(s.pos))
+    }
+
+    fn boolean(&mut self, s: StrRange) -> Result<ParserNode, TokenError> {
         debug!("#boolean");
 
         fn validation_bool_value(v: &str) -> bool {
@@ -188,46 +395,31 @@ impl<'a> ParserImpl<'a> {
             !b.is_empty() && (b[0] == b't' || b[0] == b'T' || b[0] == b'f' || b[0] == b'F')
         }
 
-        if let Ok(Token::Key(s)) = self.token_reader.next_token() {
-            let v = self.token_reader.read_value(&s);
-            if validation_bool_value(v) {
-                return Ok(self.create_node(ParseToken::Bool(v.eq_ignore_ascii_case("true"))));
-            }
+        let v = self.token_reader.read_value(&s);
+        if self.is_rfc() && v != "true" && v != "false" {
+            return Err(TokenError::Position(s.pos));
         }
-
-        Err(self.token_reader.to_error())
-    }
-
-    fn array_keys(&mut self, first_key: StrRange) -> Result<ParserNode, TokenError> {
-        let mut keys = vec![first_key];
-
-        while let Ok(Token::Comma(_)) = self.token_reader.peek_token() {
-            self.eat_token();
-            self.eat_whitespace();
-
-            match self.token_reader.next_token() {
-                Ok(Token::SingleQuoted(s)) | Ok(Token::DoubleQuoted(s)) => {
-                    keys.push(s);
-                }
-                _ => return Err(self.token_reader.to_error()),
-            }
-
-            self.eat_whitespace();
+        if validation_bool_value(v) {
+            return Ok(self.create_node(ParseToken::Bool(v.eq_ignore_ascii_case("true"))));
         }
 
-        Ok(self.create_node(ParseToken::Keys(keys)))
+        Err(self.token_reader.to_error())
     }
 
     fn array_quote_value(&mut self) -> Result<ParserNode, TokenError> {
         debug!("#array_quote_value");
-        let next = self.token_reader.next_token();
-        match next {
+        let s = self.quoted()?;
+        Ok(self.create_node(ParseToken::Key(s)))
+    }
+
+    // a quoted string, with RFC 9535 2.3.1.1 escapes only in that mode
+    fn quoted(&mut self) -> Result<StrRange, TokenError> {
+        match self.token_reader.next_token() {
             Ok(Token::SingleQuoted(s)) | Ok(Token::DoubleQuoted(s)) => {
-                if let Ok(Token::Comma(_)) = self.token_reader.peek_token() {
-                    self.array_keys(s)
-                } else {
-                    Ok(self.create_node(ParseToken::Key(s)))
+                if self.is_rfc() && !is_rfc_string(self.token_reader.read_value(&s)) {
+                    return Err(TokenError::Position(s.pos));
                 }
+                Ok(s)
             }
             _ => Err(self.token_reader.to_error()),
         }
@@ -235,29 +427,78 @@ impl<'a> ParserImpl<'a> {
 
     fn array_start(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
         debug!("#array_start");
+        let selector = self.array_selector()?;
+        self.eat_whitespace();
+
+        let selector = match self.token_reader.peek_token() {
+            Ok(Token::Comma(_)) => self.array_union(selector)?,
+            _ => selector,
+        };
+
+        Ok(ParserNode {
+            token: ParseToken::Array,
+            left: Some(Box::new(prev)),
+            right: Some(Box::new(selector)),
+        })
+    }
+
+    fn array_selector(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#array_selector");
         match self.token_reader.peek_token() {
             Ok(Token::Question(_)) => {
                 self.eat_token();
-                Ok(ParserNode {
-                    token: ParseToken::Array,
-                    left: Some(Box::new(prev)),
-                    right: Some(Box::new(self.filter()?)),
-                })
+                self.filter()
             }
             Ok(Token::Asterisk(_)) => {
                 self.eat_token();
-                Ok(ParserNode {
-                    token: ParseToken::Array,
-                    left: Some(Box::new(prev)),
-                    right: Some(Box::new(self.create_node(ParseToken::All))),
-                })
+                Ok(self.create_node(ParseToken::All))
             }
-            _ => Ok(ParserNode {
-                token: ParseToken::Array,
-                left: Some(Box::new(prev)),
-                right: Some(Box::new(self.array_value()?)),
-            }),
+            _ => self.array_value(),
+        }
+    }
+
+    //
+    // Indices only and quoted names only keep their `Union` and `Keys` tokens, any other
+    // mix becomes a `Selectors` chain: each node holds one selector on the left and the
+    // rest of the chain on the right.
+    //
+    fn array_union(&mut self, first: ParserNode) -> Result<ParserNode, TokenError> {
+        debug!("#array_union");
+        let mut selectors = vec![first];
+        while let Ok(Token::Comma(_)) = self.token_reader.peek_token() {
+            self.eat_token();
+            self.eat_whitespace();
+            selectors.push(self.array_selector()?);
+            self.eat_whitespace();
+        }
+
+        let indices: Vec<isize> = selectors.iter()
+            .filter_map(|node| match node.token {
+                ParseToken::Number(ref n) => n.as_i64().map(|n| n as isize),
+                _ => None,
+            })
+            .collect();
+        if indices.len() == selectors.len() {
+            return Ok(self.create_node(ParseToken::Union(indices)));
         }
+
+        let keys: Vec<StrRange> = selectors.iter()
+            .filter_map(|node| match &node.token {
+                ParseToken::Key(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect();
+        if keys.len() == selectors.len() {
+            return Ok(self.create_node(ParseToken::Keys(keys)));
+        }
+
+        Ok(selectors.into_iter().rev().fold(None, |rest, selector| {
+            Some(ParserNode {
+                token: ParseToken::Selectors,
+                left: Some(Box::new(selector)),
+                right: rest.map(Box::new),
+            })
+        }).unwrap())
     }
 
     fn array(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
@@ -271,14 +512,12 @@ impl<'a> ParserImpl<'a> {
         debug!("#array_value_key");
 
         if let Ok(Token::Key(s)) = self.token_reader.next_token() {
-            let val = self.token_reader.read_value(&s);
-            let digit = Self::string_to_num(val, || self.token_reader.to_error())?;
+            let digit = self.index(&s)?;
             self.eat_whitespace();
 
             match self.token_reader.peek_token() {
-                Ok(Token::Comma(_)) => self.union(digit),
                 Ok(Token::Split(_)) => self.range_from(digit),
-                _ => Ok(self.create_node(ParseToken::Number(digit as f64))),
+                _ => Ok(self.create_node(ParseToken::Number(digit.into()))),
             }
         } else {
             Err(self.token_reader.to_error())
@@ -296,6 +535,8 @@ impl<'a> ParserImpl<'a> {
             Ok(Token::DoubleQuoted(_)) | Ok(Token::SingleQuoted(_)) => {
                 self.array_quote_value()
             }
+            Ok(Token::Regex(_)) if self.mode == ParserMode::Default => self.key_regex(),
+            Ok(Token::Tilde(_)) if self.mode == ParserMode::Default => self.key_glob(),
             Err(TokenError::Eof) => Ok(self.create_node(ParseToken::Eof)),
             _ => {
                 self.eat_token();
@@ -304,28 +545,7 @@ impl<'a> ParserImpl<'a> {
         }
     }
 
-    fn union(&mut self, num: isize) -> Result<ParserNode, TokenError> {
-        debug!("#union");
-        let mut values = vec![num];
-        while matches!(self.token_reader.peek_token(), Ok(Token::Comma(_))) {
-            self.eat_token();
-            self.eat_whitespace();
-
-            match self.token_reader.next_token() {
-                Ok(Token::Key(s)) => {
-                    let val = self.token_reader.read_value(&s);
-                    let digit = Self::string_to_num(val, || self.token_reader.to_error())?;
-                    values.push(digit);
-                }
-                _ => {
-                    return Err(self.token_reader.to_error());
-                }
-            }
-        }
-        Ok(self.create_node(ParseToken::Union(values)))
-    }
-
-    fn range_value<S: FromStr>(&mut self) -> Result<Option<S>, TokenError> {
+    fn range_value(&mut self) -> Result<Option<isize>, TokenError> {
         self.eat_whitespace();
 
         match self.token_reader.peek_token() {
@@ -346,13 +566,7 @@ impl<'a> ParserImpl<'a> {
         }
 
         match self.token_reader.next_token() {
-            Ok(Token::Key(s)) => {
-                let str_step = self.token_reader.read_value(&s);
-                match Self::string_to_num(str_step, || self.token_reader.to_error()) {
-                    Ok(step) => Ok(Some(step)),
-                    Err(e) => Err(e),
-                }
-            }
+            Ok(Token::Key(s)) => Ok(Some(self.index(&s)?)),
             _ => {
                 unreachable!();
             }
@@ -387,8 +601,7 @@ impl<'a> ParserImpl<'a> {
 
         match self.token_reader.next_token() {
             Ok(Token::Key(s)) => {
-                let to_str = self.token_reader.read_value(&s);
-                let to = Self::string_to_num(to_str, || self.token_reader.to_error())?;
+                let to = self.index(&s)?;
                 let step = self.range_value()?;
                 Ok(self.create_node(ParseToken::Range(None, Some(to), step)))
             }
@@ -400,8 +613,7 @@ impl<'a> ParserImpl<'a> {
         debug!("#range");
         match self.token_reader.next_token() {
             Ok(Token::Key(s)) => {
-                let str_to = self.token_reader.read_value(&s);
-                let to = Self::string_to_num(str_to, || self.token_reader.to_error())?;
+                let to = self.index(&s)?;
                 let step = self.range_value()?;
                 Ok(self.create_node(ParseToken::Range(Some(from), Some(to), step)))
             }
@@ -411,6 +623,18 @@ impl<'a> ParserImpl<'a> {
 
     fn filter(&mut self) -> Result<ParserNode, TokenError> {
         debug!("#filter");
+        self.filter_depth += 1;
+        let node = self.filter_exprs()?;
+        self.filter_depth -= 1;
+        Ok(node)
+    }
+
+    fn filter_exprs(&mut self) -> Result<ParserNode, TokenError> {
+        if self.is_rfc() {
+            // the parentheses are part of the expression grammar in RFC 9535
+            return self.exprs();
+        }
+
         match self.token_reader.next_token() {
             Ok(Token::OpenParenthesis(_)) => {
                 let ret = self.exprs()?;
@@ -422,15 +646,49 @@ impl<'a> ParserImpl<'a> {
     }
 
     fn exprs(&mut self) -> Result<ParserNode, TokenError> {
+        let node = self.and_expr()?;
+        self.condition_expr(node)
+    }
+
+    fn and_expr(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#and_expr");
+        let mut node = self.exprs_term()?;
+        while let Ok(Token::And(_)) = self.token_reader.peek_token() {
+            self.eat_token();
+            node = ParserNode {
+                token: ParseToken::Filter(FilterToken::And),
+                left: Some(Box::new(node)),
+                right: Some(Box::new(self.exprs_term()?)),
+            };
+        }
+        Ok(node)
+    }
+
+    fn exprs_term(&mut self) -> Result<ParserNode, TokenError> {
         self.eat_whitespace();
-        debug!("#exprs");
+        debug!("#exprs_term");
         let node = match self.token_reader.peek_token() {
-            Ok(Token::OpenParenthesis(_)) => {
+            Ok(Token::Not(_)) => {
                 self.eat_token();
-                trace!("\t-exprs - open_parenthesis");
-                let ret = self.exprs()?;
                 self.eat_whitespace();
-                self.close_token(ret, Token::CloseParenthesis(StrRange::new(0, 0)))?
+                trace!("\t-exprs - not");
+                self.not_expr()?
+            }
+            Ok(Token::OpenParenthesis(_)) if self.mode == ParserMode::Default => {
+                trace!("\t-exprs - open_parenthesis");
+                // `(@.a + 1) * 2 > 3` opens with an operand, not a sub-expression
+                let token_reader = self.token_reader.clone();
+                match self.expr() {
+                    Ok(node) => node,
+                    Err(_) => {
+                        self.token_reader = token_reader;
+                        self.paren_expr()?
+                    }
+                }
+            }
+            Ok(Token::OpenParenthesis(_)) => {
+                trace!("\t-exprs - open_parenthesis");
+                self.paren_expr()?
             }
             _ => {
                 trace!("\t-exprs - else");
@@ -438,27 +696,49 @@ impl<'a> ParserImpl<'a> {
             }
         };
         self.eat_whitespace();
-        self.condition_expr(node)
+        Ok(node)
+    }
+
+    fn paren_expr(&mut self) -> Result<ParserNode, TokenError> {
+        self.eat_token();
+        let ret = self.exprs()?;
+        self.eat_whitespace();
+        self.close_token(ret, Token::CloseParenthesis(StrRange::new(0, 0)))
+    }
+
+    // `!` negates a parenthesised expression or an existence test, not a comparison
+    fn not_expr(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#not_expr");
+        let node = match self.token_reader.peek_token() {
+            Ok(Token::OpenParenthesis(_)) => self.paren_expr()?,
+            _ => {
+                let node = self.expr()?;
+                if let ParseToken::Filter(_) = node.token {
+                    return Err(self.token_reader.to_error());
+                }
+                node
+            }
+        };
+
+        Ok(ParserNode {
+            token: ParseToken::Filter(FilterToken::Not),
+            left: Some(Box::new(node)),
+            right: None,
+        })
     }
 
+    // `&&` binds tighter than `||`, and both associate to the left
     fn condition_expr(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
         debug!("#condition_expr");
         match self.token_reader.peek_token() {
-            Ok(Token::And(_)) => {
-                self.eat_token();
-                Ok(ParserNode {
-                    token: ParseToken::Filter(FilterToken::And),
-                    left: Some(Box::new(prev)),
-                    right: Some(Box::new(self.exprs()?)),
-                })
-            }
             Ok(Token::Or(_)) => {
                 self.eat_token();
-                Ok(ParserNode {
+                let node = ParserNode {
                     token: ParseToken::Filter(FilterToken::Or),
                     left: Some(Box::new(prev)),
-                    right: Some(Box::new(self.exprs()?)),
-                })
+                    right: Some(Box::new(self.and_expr()?)),
+                };
+                self.condition_expr(node)
             }
             _ => Ok(prev),
         }
@@ -467,101 +747,438 @@ impl<'a> ParserImpl<'a> {
     fn expr(&mut self) -> Result<ParserNode, TokenError> {
         debug!("#expr");
 
-        let has_prop_candidate = matches!(self.token_reader.peek_token(), Ok(Token::At(_)));
+        let has_prop_candidate = match self.token_reader.peek_token() {
+            Ok(Token::At(_)) => true,
+            Ok(Token::Absolute(_)) => self.is_rfc(),
+            _ => false,
+        };
 
-        let node = self.term()?;
+        let node = self.arith_expr()?;
         self.eat_whitespace();
 
+        let is_test_function = matches!(node.function_type(),
+            Some(FunctionType::Logical) | Some(FunctionType::Nodes));
+
+        if let Some(token) = self.member_op() {
+            self.eat_token();
+            self.eat_whitespace();
+            return Ok(ParserNode {
+                token: ParseToken::Filter(token),
+                left: Some(Box::new(node)),
+                right: Some(Box::new(self.member_set()?)),
+            });
+        }
+
         if matches!(self.token_reader.peek_token(),
             Ok(Token::Equal(_))
             | Ok(Token::NotEqual(_))
             | Ok(Token::Little(_))
             | Ok(Token::LittleOrEqual(_))
             | Ok(Token::Greater(_))
-            | Ok(Token::GreaterOrEqual(_)))
+            | Ok(Token::GreaterOrEqual(_))
+            | Ok(Token::Matches(_)))
         {
+            if (self.is_rfc() || node.function_type().is_some()) && !node.is_comparable() {
+                return Err(self.token_reader.to_error());
+            }
             self.op(node)
-        } else if has_prop_candidate {
+        } else if (has_prop_candidate || is_test_function) && !node.is_arithmetic() {
             Ok(node)
         } else {
             Err(self.token_reader.to_error())
         }
     }
 
-    fn term_num(&mut self) -> Result<ParserNode, TokenError> {
-        debug!("#term_num");
-        match self.token_reader.next_token() {
-            Ok(Token::Key(s)) => {
-                let val = self.token_reader.read_value(&s);
-                match self.token_reader.peek_token() {
-                    Ok(Token::Dot(_)) => self.term_num_float(val),
-                    _ => {
-                        let number = Self::string_to_num(val, || self.token_reader.to_error())?;
-                        Ok(self.create_node(ParseToken::Number(number)))
-                    }
-                }
-            }
-            _ => Err(self.token_reader.to_error()),
+    // `*`, `/` and `%` bind tighter than `+` and `-`, and all of them associate to the left
+    fn arith_expr(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#arith_expr");
+        let mut node = self.arith_term()?;
+        while let Some(token) = self.arith_op(false) {
+            self.eat_token();
+            self.eat_whitespace();
+            node = ParserNode {
+                token: ParseToken::Arithmetic(token),
+                left: Some(Box::new(node)),
+                right: Some(Box::new(self.arith_term()?)),
+            };
         }
+        Ok(node)
     }
 
-    fn term_num_float(&mut self, num: &'a str) -> Result<ParserNode, TokenError> {
-        debug!("#term_num_float");
-        self.eat_token();
-        match self.token_reader.next_token() {
-            Ok(Token::Key(s)) => {
-                let frac = self.token_reader.read_value(&s);
-                let number = Self::string_to_num(&[num, ".", frac].concat(), || self.token_reader.to_error())?;
-                Ok(self.create_node(ParseToken::Number(number)))
-            }
-            _ => Err(self.token_reader.to_error()),
+    fn arith_term(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#arith_term");
+        let mut node = self.arith_factor()?;
+        while let Some(token) = self.arith_op(true) {
+            self.eat_token();
+            self.eat_whitespace();
+            node = ParserNode {
+                token: ParseToken::Arithmetic(token),
+                left: Some(Box::new(node)),
+                right: Some(Box::new(self.arith_factor()?)),
+            };
         }
+        Ok(node)
     }
 
-    fn term(&mut self) -> Result<ParserNode, TokenError> {
-        debug!("#term");
+    fn arith_factor(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#arith_factor");
+        match self.token_reader.peek_token() {
+            Ok(Token::OpenParenthesis(_)) if self.mode == ParserMode::Default => {
+                self.eat_token();
+                self.eat_whitespace();
+                let node = self.arith_expr()?;
+                self.eat_whitespace();
+                self.close_token(node, Token::CloseParenthesis(StrRange::new(0, 0)))
+            }
+            _ => self.term(),
+        }
+    }
 
-        if self.token_reader.peek_token().is_err() {
-            return Err(self.token_reader.to_error());
+    //
+    // `+`, `-`, `/` and `%` are read as keys, so they are operators only when they
+    // stand alone, e.g. `@.end - @.start`. Arithmetic is not part of RFC 9535.
+    //
+    fn arith_op(&mut self, is_multiplicative: bool) -> Option<ArithmeticToken> {
+        if self.is_rfc() {
+            return None;
         }
 
-        let has_term_key = if let Ok(Token::Key(s)) = self.token_reader.peek_token() {
-            Some(s.clone())
-        } else {
-            None
+        self.eat_whitespace();
+        let key = match self.token_reader.peek_token() {
+            Ok(Token::Asterisk(_)) if is_multiplicative => return Some(ArithmeticToken::Multiply),
+            Ok(Token::Key(s)) => s.clone(),
+            _ => return None,
         };
 
-        if let Some(s) = has_term_key {
-            let key = self.token_reader.read_value(&s);
-            return match key.as_bytes()[0] {
-                b'-' | b'0'..=b'9' => self.term_num(),
-                _ => self.boolean(),
-            };
+        match (self.token_reader.read_value(&key), is_multiplicative) {
+            ("+", false) => Some(ArithmeticToken::Add),
+            ("-", false) => Some(ArithmeticToken::Subtract),
+            ("/", true) => Some(ArithmeticToken::Divide),
+            ("%", true) => Some(ArithmeticToken::Remainder),
+            _ => None,
         }
+    }
 
-        match self.token_reader.peek_token() {
-            Ok(Token::At(_)) => {
-                self.eat_token();
+    fn function(&mut self, name: StrRange) -> Result<ParserNode, TokenError> {
+        debug!("#function");
+        let function = self.function_token(&name).ok_or(TokenError::Position(name.pos))?;
+        self.eat_token();
 
-                let node = self.create_node(ParseToken::Relative);
-                match self.token_reader.peek_token() {
-                    Ok(Token::Whitespace(_)) => {
-                        self.eat_whitespace();
-                        Ok(node)
-                    }
-                    _ => self.paths(node),
+        let mut args = Vec::new();
+        for (i, param) in function.parameters().iter().enumerate() {
+            self.eat_whitespace();
+            if i > 0 {
+                match self.token_reader.next_token() {
+                    Ok(Token::Comma(_)) => self.eat_whitespace(),
+                    _ => return Err(self.token_reader.to_error()),
                 }
             }
-            Ok(Token::Absolute(_)) => {
-                self.json_path()
-            }
-            Ok(Token::DoubleQuoted(_)) | Ok(Token::SingleQuoted(_)) => {
-                self.array_quote_value()
-            }
-            _ => {
-                Err(self.token_reader.to_error())
-            }
+            args.push(self.function_argument(*param)?);
+            self.eat_whitespace();
         }
+
+        let args = args.into_iter().rev().fold(None, |next, arg| Some(Box::new(ParserNode {
+            token: ParseToken::Argument,
+            left: Some(Box::new(arg)),
+            right: next,
+        })));
+        let node = ParserNode {
+            token: ParseToken::Function(function),
+            left: args,
+            right: None,
+        };
+        self.close_token(node, Token::CloseParenthesis(StrRange::new(0, 0)))
+    }
+
+    // a standard function, or else one of the registry
+    fn function_token(&self, name: &StrRange) -> Option<FunctionToken> {
+        let name = self.token_reader.read_value(name);
+        FunctionToken::from_name(name).or_else(|| {
+            self.functions.get(name).map(|function| FunctionToken::Custom {
+                name: name.to_string(),
+                parameters: function.parameters().to_vec(),
+                result: function.result(),
+            })
+        })
+    }
+
+    // RFC 9535 2.4.3: the arguments a parameter type accepts
+    fn function_argument(&mut self, param: FunctionType) -> Result<ParserNode, TokenError> {
+        debug!("#function_argument");
+        if param == FunctionType::Logical {
+            return self.exprs();
+        }
+
+        let node = match self.token_reader.peek_token() {
+            Ok(Token::DoubleQuoted(_)) | Ok(Token::SingleQuoted(_)) => {
+                let s = self.quoted()?;
+                self.create_node(ParseToken::Key(s))
+            }
+            _ => self.term()?,
+        };
+
+        let is_valid = match param {
+            FunctionType::Value => node.is_comparable(),
+            _ => node.is_query() || node.function_type() == Some(FunctionType::Nodes),
+        };
+        if !is_valid {
+            return Err(self.token_reader.to_error());
+        }
+        Ok(node)
+    }
+
+    fn member_op(&mut self) -> Option<FilterToken> {
+        let key = match self.token_reader.peek_token() {
+            Ok(Token::Key(s)) if self.mode == ParserMode::Default => s.clone(),
+            _ => return None,
+        };
+
+        match self.token_reader.read_value(&key) {
+            "in" => Some(FilterToken::In),
+            "nin" => Some(FilterToken::Nin),
+            "subsetof" => Some(FilterToken::SubsetOf),
+            "anyof" => Some(FilterToken::AnyOf),
+            "noneof" => Some(FilterToken::NoneOf),
+            _ => None,
+        }
+    }
+
+    fn member_set(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#member_set");
+        let key = match self.token_reader.peek_token() {
+            Ok(Token::Key(s)) => Some(s.clone()),
+            _ => None,
+        };
+        let is_parameter = key.is_some_and(|s| self.token_reader.read_value(&s).starts_with('$'));
+
+        match self.token_reader.peek_token() {
+            Ok(Token::OpenArray(_)) => {
+                self.eat_token();
+                let value = self.json_array()?;
+                Ok(self.create_node(ParseToken::Literal(value)))
+            }
+            Ok(Token::At(_)) | Ok(Token::Absolute(_)) => self.term(),
+            Ok(Token::Key(_)) if is_parameter => self.term(),
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    fn json_array(&mut self) -> Result<Value, TokenError> {
+        debug!("#json_array");
+        let mut vec = Vec::new();
+        self.eat_whitespace();
+        if let Ok(Token::CloseArray(_)) = self.token_reader.peek_token() {
+            self.eat_token();
+            return Ok(Value::Array(vec));
+        }
+
+        loop {
+            vec.push(self.json_value()?);
+            self.eat_whitespace();
+            match self.token_reader.next_token() {
+                Ok(Token::Comma(_)) => self.eat_whitespace(),
+                Ok(Token::CloseArray(_)) => return Ok(Value::Array(vec)),
+                _ => return Err(self.token_reader.to_error()),
+            }
+        }
+    }
+
+    fn json_object(&mut self) -> Result<Value, TokenError> {
+        debug!("#json_object");
+        let mut map = Map::new();
+        self.eat_whitespace();
+        if let Ok(Token::CloseBrace(_)) = self.token_reader.peek_token() {
+            self.eat_token();
+            return Ok(Value::Object(map));
+        }
+
+        loop {
+            let key = self.json_string()?;
+            self.eat_whitespace();
+            match self.token_reader.next_token() {
+                Ok(Token::Split(_)) => self.eat_whitespace(),
+                _ => return Err(self.token_reader.to_error()),
+            }
+            map.insert(key, self.json_value()?);
+            self.eat_whitespace();
+            match self.token_reader.next_token() {
+                Ok(Token::Comma(_)) => self.eat_whitespace(),
+                Ok(Token::CloseBrace(_)) => return Ok(Value::Object(map)),
+                _ => return Err(self.token_reader.to_error()),
+            }
+        }
+    }
+
+    fn json_string(&mut self) -> Result<String, TokenError> {
+        let s = self.quoted()?;
+        let quoted = self.token_reader.read_value(&s);
+        Ok(unescape(&quoted[1..quoted.len() - 1]))
+    }
+
+    fn json_value(&mut self) -> Result<Value, TokenError> {
+        debug!("#json_value");
+        match self.token_reader.peek_token() {
+            Ok(Token::OpenArray(_)) => {
+                self.eat_token();
+                self.json_array()
+            }
+            Ok(Token::OpenBrace(_)) => {
+                self.eat_token();
+                self.json_object()
+            }
+            Ok(Token::SingleQuoted(_)) | Ok(Token::DoubleQuoted(_)) => self.json_string().map(Value::String),
+            Ok(Token::Key(_)) => match self.term()?.token {
+                ParseToken::Number(n) => Ok(Value::Number(n)),
+                ParseToken::Bool(b) => Ok(Value::Bool(b)),
+                ParseToken::Null => Ok(Value::Null),
+                _ => Err(self.token_reader.to_error()),
+            },
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    fn term_num(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#term_num");
+        match self.token_reader.next_token() {
+            Ok(Token::Key(s)) => match self.token_reader.peek_token() {
+                Ok(Token::Dot(_)) => self.term_num_float(s),
+                _ => self.number(&s, self.token_reader.read_value(&s)),
+            },
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    fn term_num_float(&mut self, int: StrRange) -> Result<ParserNode, TokenError> {
+        debug!("#term_num_float");
+        self.eat_token();
+        match self.token_reader.next_token() {
+            Ok(Token::Key(frac)) => {
+                let literal = [self.token_reader.read_value(&int), ".", self.token_reader.read_value(&frac)].concat();
+                self.number(&int, &literal)
+            }
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    // RFC 8259 6, shared by RFC 9535 2.3.5.1, keeping integers exact. The default grammar
+    // also takes what Rust reads as a float, such as `01`.
+    fn number(&mut self, s: &StrRange, literal: &str) -> Result<ParserNode, TokenError> {
+        let number = match Number::from_str(literal) {
+            Ok(n) => Some(n),
+            Err(_) if self.mode == ParserMode::Default => literal.parse().ok().and_then(Number::from_f64),
+            Err(_) => None,
+        };
+        match number {
+            Some(n) => Ok(self.create_node(ParseToken::Number(n))),
+            None => Err(TokenError::Position(s.pos)),
+        }
+    }
+
+    fn term(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#term");
+
+        if self.token_reader.peek_token().is_err() {
+            return Err(self.token_reader.to_error());
+        }
+
+        let has_term_key = if let Ok(Token::Key(s)) = self.token_reader.peek_token() {
+            Some(s.clone())
+        } else {
+            None
+        };
+
+        if let Some(s) = has_term_key {
+            let key = self.token_reader.read_value(&s);
+            if let b'-' | b'0'..=b'9' = key.as_bytes()[0] {
+                return self.term_num();
+            }
+            if key.starts_with('$') {
+                return self.parameter(s);
+            }
+
+            self.eat_token();
+            return match self.token_reader.peek_token() {
+                Ok(Token::OpenParenthesis(_)) => self.function(s),
+                _ if key == "null" => Ok(self.create_node(ParseToken::Null)),
+                _ => self.boolean(s),
+            };
+        }
+
+        match self.token_reader.peek_token() {
+            Ok(Token::At(_)) => {
+                self.eat_token();
+
+                let node = self.create_node(ParseToken::Relative);
+                match self.token_reader.peek_token() {
+                    Ok(Token::Whitespace(_)) if self.mode == ParserMode::Default => {
+                        self.eat_whitespace();
+                        Ok(node)
+                    }
+                    _ => self.paths(node),
+                }
+            }
+            Ok(Token::Absolute(_)) => {
+                self.json_path()
+            }
+            Ok(Token::Context(_)) if self.mode == ParserMode::Default => {
+                self.context()
+            }
+            Ok(Token::OpenArray(_)) | Ok(Token::OpenBrace(_)) if self.mode == ParserMode::Default => {
+                let value = self.json_value()?;
+                Ok(self.create_node(ParseToken::Literal(value)))
+            }
+            Ok(Token::DoubleQuoted(_)) | Ok(Token::SingleQuoted(_)) => {
+                let s = self.quoted()?;
+                Ok(self.create_node(ParseToken::Key(s)))
+            }
+            _ => {
+                Err(self.token_reader.to_error())
+            }
+        }
+    }
+
+    // `$name`: a letter or `_`, then letters, digits or `_`
+    fn parameter(&mut self, s: StrRange) -> Result<ParserNode, TokenError> {
+        debug!("#parameter");
+        let name = &self.token_reader.read_value(&s)[1..];
+        let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if self.is_rfc() || !is_name {
+            return Err(TokenError::Position(s.pos));
+        }
+        self.eat_token();
+
+        if !self.parameters.contains(&name) {
+            self.parameters.push(name);
+        }
+        Ok(self.create_node(ParseToken::Parameter(StrRange::new(s.pos + 1, s.offset - 1))))
+    }
+
+    fn context(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#context");
+        let token = match self.token_reader.next_token() {
+            Ok(Token::Context(s)) => match self.token_reader.read_value(&s) {
+                "@property" => ContextToken::Property,
+                "@parentProperty" => ContextToken::ParentProperty,
+                "@path" => ContextToken::Path,
+                _ => {
+                    // @root is another name for $
+                    let node = self.create_node(ParseToken::Absolute);
+                    return self.paths(node);
+                }
+            },
+            _ => return Err(self.token_reader.to_error()),
+        };
+        self.has_context = true;
+        Ok(self.create_node(ParseToken::Context(token)))
+    }
+
+    fn comparable(&mut self) -> Result<ParserNode, TokenError> {
+        let node = self.arith_expr()?;
+        if (self.is_rfc() || node.function_type().is_some()) && !node.is_comparable() {
+            return Err(self.token_reader.to_error());
+        }
+        Ok(node)
     }
 
     fn op(&mut self, prev: ParserNode) -> Result<ParserNode, TokenError> {
@@ -573,6 +1190,14 @@ impl<'a> ParserImpl<'a> {
             Ok(Token::LittleOrEqual(_)) => ParseToken::Filter(FilterToken::LittleOrEqual),
             Ok(Token::Greater(_)) => ParseToken::Filter(FilterToken::Greater),
             Ok(Token::GreaterOrEqual(_)) => ParseToken::Filter(FilterToken::GreaterOrEqual),
+            Ok(Token::Matches(_)) if self.mode == ParserMode::Default => {
+                self.eat_whitespace();
+                return Ok(ParserNode {
+                    token: ParseToken::Filter(FilterToken::Matches),
+                    left: Some(Box::new(prev)),
+                    right: Some(Box::new(self.regex()?)),
+                });
+            }
             _ => {
                 return Err(self.token_reader.to_error());
             }
@@ -583,10 +1208,53 @@ impl<'a> ParserImpl<'a> {
         Ok(ParserNode {
             token,
             left: Some(Box::new(prev)),
-            right: Some(Box::new(self.term()?)),
+            right: Some(Box::new(self.comparable()?)),
         })
     }
 
+    fn regex(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#regex");
+        match self.token_reader.next_token() {
+            Ok(Token::Regex(s)) => {
+                self.insert_regex(&s, compile_regex)?;
+                Ok(self.create_node(ParseToken::Regex(s)))
+            }
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    fn key_regex(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#key_regex");
+        match self.token_reader.next_token() {
+            Ok(Token::Regex(s)) => {
+                self.insert_regex(&s, compile_regex)?;
+                Ok(self.create_node(ParseToken::KeyPattern(s)))
+            }
+            _ => Err(self.token_reader.to_error()),
+        }
+    }
+
+    // `~'pattern'`, a quoted name is only a glob behind the `~`
+    fn key_glob(&mut self) -> Result<ParserNode, TokenError> {
+        debug!("#key_glob");
+        self.eat_token();
+        let s = self.quoted()?;
+        self.insert_regex(&s, compile_glob)?;
+        Ok(self.create_node(ParseToken::KeyPattern(s)))
+    }
+
+    fn insert_regex<F>(&mut self, s: &StrRange, compile: F) -> Result<(), TokenError>
+        where
+            F: Fn(&str) -> Option<Regex>
+    {
+        let literal = self.token_reader.read_value(s);
+        if !self.regexes.contains_key(literal) {
+            let regex = compile(literal).ok_or(TokenError::Position(s.pos))?;
+            self.regexes.insert(literal, Rc::new(regex));
+        }
+        Ok(())
+    }
+
     fn eat_whitespace(&mut self) {
         while let Ok(Token::Whitespace(_)) = self.token_reader.peek_token() {
             let _ = self.token_reader.next_token();
@@ -621,29 +1289,180 @@ pub struct ParserNode {
     pub token: ParseToken,
 }
 
+impl ParserNode {
+    // a literal, a singular query or a ValueType function, the only comparison operands RFC 9535 allows
+    fn is_comparable(&self) -> bool {
+        match &self.token {
+            ParseToken::Number(_)
+            | ParseToken::Bool(_)
+            | ParseToken::Null
+            | ParseToken::Key(_)
+            | ParseToken::Literal(_) => true,
+            ParseToken::Function(f) => f.result() == FunctionType::Value,
+            ParseToken::Arithmetic(_) | ParseToken::Context(_) | ParseToken::Aggregate(_) | ParseToken::Parameter(_) => true,
+            _ => self.is_singular_query(),
+        }
+    }
+
+    fn is_arithmetic(&self) -> bool {
+        matches!(self.token, ParseToken::Arithmetic(_))
+    }
+
+    fn is_query(&self) -> bool {
+        matches!(self.token,
+            ParseToken::Absolute
+            | ParseToken::Relative
+            | ParseToken::In
+            | ParseToken::Leaves
+            | ParseToken::Array)
+    }
+
+    fn function_type(&self) -> Option<FunctionType> {
+        match &self.token {
+            ParseToken::Function(f) => Some(f.result()),
+            _ => None,
+        }
+    }
+
+    fn is_singular_query(&self) -> bool {
+        match &self.token {
+            ParseToken::Absolute | ParseToken::Relative => true,
+            ParseToken::In | ParseToken::Array => {
+                let is_singular_segment = match self.right.as_ref().map(|n| &n.token) {
+                    Some(ParseToken::Key(_)) => true,
+                    Some(ParseToken::Number(_)) => self.token == ParseToken::Array,
+                    _ => false,
+                };
+                is_singular_segment && self.left.as_ref().is_some_and(|n| n.is_singular_query())
+            }
+            _ => false,
+        }
+    }
+}
+
+// `/pattern/flags` with the flags `i`, `m`, `s` and `x`, each at most once
+fn compile_regex(literal: &str) -> Option<Regex> {
+    let end = literal.rfind('/').filter(|end| *end > 0)?;
+    let mut builder = RegexBuilder::new(&literal[1..end]);
+    let mut flags = String::new();
+    for flag in literal[end + 1..].chars() {
+        if flags.contains(flag) {
+            return None;
+        }
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            _ => return None,
+        };
+        flags.push(flag);
+    }
+    utils::build_regex(&mut builder)
+}
+
+// RFC 9535 2.3.1.1: no control characters, and only JSON escapes or that of the own quote
+fn is_rfc_string(quoted: &str) -> bool {
+    let quote = &quoted[..1];
+    let mut is_escaped = false;
+    for ch in quoted[1..quoted.len() - 1].chars() {
+        match ch {
+            _ if is_escaped => {
+                if !matches!(ch, 'b' | 'f' | 'n' | 'r' | 't' | '/' | '\\' | 'u') && !quote.starts_with(ch) {
+                    return false;
+                }
+                is_escaped = false;
+            }
+            '\\' => is_escaped = true,
+            _ if ch < '\u{20}' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+// `*` matches any run of characters and `\*` a literal `*`, the whole name must match
+fn compile_glob(literal: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut part = String::new();
+    let mut is_escaped = false;
+    for ch in literal[1..literal.len() - 1].chars() {
+        match ch {
+            '*' if !is_escaped => {
+                pattern.push_str(&regex::escape(&unescape(&part)));
+                pattern.push_str(".*");
+                part.clear();
+            }
+            _ => {
+                is_escaped = !is_escaped && ch == '\\';
+                part.push(ch);
+            }
+        }
+    }
+    pattern.push_str(&regex::escape(&unescape(&part)));
+    pattern.push('$');
+
+    utils::build_regex(RegexBuilder::new(&pattern).dot_matches_new_line(true))
+}
+
+// in a filter, a dot-notation name like `x-1` or `x-` reads as arithmetic written without
+// the blank space around the operator, so it must be quoted, e.g. `@['x-1']`
+fn find_arith_op(name: &str) -> Option<usize> {
+    name.char_indices()
+        .find(|(i, c)| {
+            *i > 0 && matches!(c, '+' | '-' | '/' | '%')
+                && name[i + 1..].chars().next().is_none_or(|next| next.is_ascii_digit())
+        })
+        .map(|(i, _)| i)
+}
+
+// RFC 9535 2.1: int = "0" / (["-"] DIGIT1 *DIGIT)
+fn is_rfc_int(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    match digits.as_bytes() {
+        [b'0'] => s == "0",
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+// array indices and slice bounds must stay within the I-JSON range
+fn is_rfc_index(s: &str) -> bool {
+    const MAX_INDEX: i64 = (1 << 53) - 1;
+    is_rfc_int(s) && s.parse::<i64>().is_ok_and(|n| (-MAX_INDEX..=MAX_INDEX).contains(&n))
+}
+
 #[cfg(test)]
 mod path_parser_tests {
+    use std::rc::Rc;
+
+    use serde_json::{json, Number};
+
     use paths::ParserTokenHandler;
-    use paths::path_parser::PathParser;
+    use paths::function_registry::FunctionRegistry;
+    use paths::path_parser::{ParserMode, PathParser};
     use paths::str_reader::StrRange;
-    use paths::tokens::{FilterToken, ParseToken};
+    use paths::tokenizer::TokenError;
+    use paths::tokens::{AggregateToken, ArithmeticToken, ContextToken, FilterToken, FunctionToken, FunctionType, ParseToken};
 
     struct NodeVisitorTestImpl<'a> {
         input: &'a str,
+        mode: ParserMode,
         stack: Vec<ParseToken>,
     }
 
     impl<'a> NodeVisitorTestImpl<'a> {
-        fn new(input: &'a str) -> Self {
+        fn new(input: &'a str, mode: ParserMode) -> Self {
             NodeVisitorTestImpl {
                 input,
+                mode,
                 stack: Vec::new(),
             }
         }
 
         fn start(&mut self) -> Result<Vec<ParseToken>, String> {
-            let parser = PathParser::compile(self.input).map_err(|_| "Token Error")?;
-            let _ = parser.parse(self);
+            let parser = PathParser::compile_with_mode(self.input, self.mode).map_err(|_| "Token Error")?;
+            parser.parse(self)?;
             Ok(self.stack.split_off(0))
         }
     }
@@ -663,7 +1482,12 @@ mod path_parser_tests {
     }
 
     fn run(input: &str) -> Result<Vec<ParseToken>, String> {
-        let mut interpreter = NodeVisitorTestImpl::new(input);
+        let mut interpreter = NodeVisitorTestImpl::new(input, ParserMode::Default);
+        interpreter.start()
+    }
+
+    fn run_rfc9535(input: &str) -> Result<Vec<ParseToken>, String> {
+        let mut interpreter = NodeVisitorTestImpl::new(input, ParserMode::Rfc9535);
         interpreter.start()
     }
 
@@ -695,6 +1519,69 @@ mod path_parser_tests {
         invalid("$[?(1 = >)]");
     }
 
+    #[test]
+    fn parse_member_name() {
+        setup();
+
+        let error = |path| PathParser::compile_with_mode(path, ParserMode::Rfc9535).err();
+
+        assert_eq!(error("$.가격.prix_€.a\u{3000}b"), None);
+        assert_eq!(error("$.a-b"), Some(TokenError::Position(3)));
+        assert_eq!(error("$.1a"), Some(TokenError::Position(2)));
+        assert_eq!(error("$.a1.b#"), Some(TokenError::Position(6)));
+        assert_eq!(error("$.a b"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.a\t"), Some(TokenError::Position(3)));
+
+        // only space, tab, line feed and carriage return are blank, U+3000 belongs to the name
+        assert_eq!(
+            run("$.a\u{3000}b"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "a\u{3000}b".len()))
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_number() {
+        setup();
+
+        let number = |path: &str| match run(path) {
+            Ok(tokens) => tokens.into_iter().find_map(|t| match t {
+                ParseToken::Number(n) => Some(n),
+                _ => None,
+            }),
+            _ => None,
+        };
+
+        assert_eq!(number("$[?(@.a == 9007199254740993)]"), Some(9007199254740993_i64.into()));
+        assert_eq!(number("$[?(@.a == 18446744073709551615)]"), Some(18446744073709551615_u64.into()));
+        if cfg!(feature = "arbitrary_precision") {
+            // a number is kept as written, so only its value is compared
+            assert_eq!(number("$[?(@.a == -1.5)]").and_then(|n| n.as_f64()), Some(-1.5));
+            assert_eq!(number("$[?(@.a == 1e6)]").and_then(|n| n.as_f64()), Some(1e6));
+            assert_eq!(number("$[?(@.a == -2.5E-3)]").and_then(|n| n.as_f64()), Some(-2.5e-3));
+        } else {
+            assert_eq!(number("$[?(@.a == -1.5)]"), Number::from_f64(-1.5));
+            assert_eq!(number("$[?(@.a == 1e6)]"), Number::from_f64(1e6));
+            assert_eq!(number("$[?(@.a == -2.5E-3)]"), Number::from_f64(-2.5e-3));
+        }
+        assert_eq!(number("$[1e1]"), Some(10.into()));
+
+        assert!(run("$[?(@.a == 1.)]").is_err());
+        // a number beyond f64 is kept as written by `arbitrary_precision`
+        if cfg!(feature = "arbitrary_precision") {
+            assert!(run("$[?(@.a == 1e999)]").is_ok());
+        } else {
+            assert!(run("$[?(@.a == 1e999)]").is_err());
+        }
+        assert!(run("$[?(@.a == -)]").is_err());
+        assert!(run("$[1.5]").is_err());
+        assert!(run_rfc9535("$[?@.a == 01]").is_err());
+        assert!(run_rfc9535("$[1e1]").is_err());
+    }
+
     #[test]
     fn parse_path() {
         setup();
@@ -752,7 +1639,7 @@ mod path_parser_tests {
                 ParseToken::Absolute,
                 ParseToken::Leaves,
                 ParseToken::Array,
-                ParseToken::Number(0.0),
+                ParseToken::Number(0.into()),
                 ParseToken::ArrayEof
             ])
         );
@@ -853,10 +1740,10 @@ mod path_parser_tests {
                 ParseToken::In,
                 ParseToken::Key(StrRange::new(2, "a".len())),
                 ParseToken::Array,
-                ParseToken::Number(0_f64),
+                ParseToken::Number(0.into()),
                 ParseToken::ArrayEof,
                 ParseToken::Array,
-                ParseToken::Number(1_f64),
+                ParseToken::Number(1.into()),
                 ParseToken::ArrayEof
             ])
         );
@@ -960,11 +1847,31 @@ mod path_parser_tests {
         );
 
         assert_eq!(
-            run(r#"$["a", 'b']"#),
+            run(r#"$[5:0:-2]"#),
             Ok(vec![
                 ParseToken::Absolute,
                 ParseToken::Array,
-                ParseToken::Keys(vec![StrRange::new(2, "\"a\"".len()), StrRange::new(7, "'b'".len())]),
+                ParseToken::Range(Some(5), Some(0), Some(-2)),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run_rfc9535(r#"$[::-1]"#),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Range(None, None, Some(-1)),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run(r#"$["a", 'b']"#),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Keys(vec![StrRange::new(2, "\"a\"".len()), StrRange::new(7, "'b'".len())]),
                 ParseToken::ArrayEof
             ])
         );
@@ -976,8 +1883,8 @@ mod path_parser_tests {
                 ParseToken::In,
                 ParseToken::Key(StrRange::new(2, "a".len())),
                 ParseToken::Array,
-                ParseToken::Number(1_f64),
-                ParseToken::Number(2_f64),
+                ParseToken::Number(1.into()),
+                ParseToken::Number(2.into()),
                 ParseToken::Filter(FilterToken::Greater),
                 ParseToken::ArrayEof
             ])
@@ -993,7 +1900,7 @@ mod path_parser_tests {
                 ParseToken::Absolute,
                 ParseToken::In,
                 ParseToken::Key(StrRange::new(8, "b".len())),
-                ParseToken::Number(3_f64),
+                ParseToken::Number(3.into()),
                 ParseToken::Filter(FilterToken::Greater),
                 ParseToken::ArrayEof
             ])
@@ -1011,8 +1918,8 @@ mod path_parser_tests {
                 ParseToken::In,
                 ParseToken::Key(StrRange::new(10, "c".len())),
                 ParseToken::Filter(FilterToken::Greater),
-                ParseToken::Number(1_f64),
-                ParseToken::Number(2_f64),
+                ParseToken::Number(1.into()),
+                ParseToken::Number(2.into()),
                 ParseToken::Filter(FilterToken::Equal),
                 ParseToken::Filter(FilterToken::And),
                 ParseToken::ArrayEof
@@ -1031,11 +1938,11 @@ mod path_parser_tests {
                 ParseToken::In,
                 ParseToken::Key(StrRange::new(10, "d".len())),
                 ParseToken::Filter(FilterToken::Greater),
-                ParseToken::Number(1_f64),
-                ParseToken::Number(2_f64),
+                ParseToken::Number(1.into()),
+                ParseToken::Number(2.into()),
                 ParseToken::Filter(FilterToken::Equal),
-                ParseToken::Number(3_f64),
-                ParseToken::Number(4_f64),
+                ParseToken::Number(3.into()),
+                ParseToken::Number(4.into()),
                 ParseToken::Filter(FilterToken::GreaterOrEqual),
                 ParseToken::Filter(FilterToken::Or),
                 ParseToken::Filter(FilterToken::And),
@@ -1110,7 +2017,7 @@ mod path_parser_tests {
                 ParseToken::Absolute,
                 ParseToken::Array,
                 ParseToken::Relative,
-                ParseToken::Number(1_f64),
+                ParseToken::Number(1.into()),
                 ParseToken::Filter(FilterToken::Greater),
                 ParseToken::ArrayEof
             ])
@@ -1156,8 +2063,8 @@ mod path_parser_tests {
             Ok(vec![
                 ParseToken::Absolute,
                 ParseToken::Array,
-                ParseToken::Number(1.1),
-                ParseToken::Number(2.1),
+                ParseToken::Number(Number::from_f64(1.1).unwrap()),
+                ParseToken::Number(Number::from_f64(2.1).unwrap()),
                 ParseToken::Filter(FilterToken::Little),
                 ParseToken::ArrayEof
             ])
@@ -1179,4 +2086,765 @@ mod path_parser_tests {
             panic!();
         }
     }
+
+    #[test]
+    fn parse_rfc9535() {
+        setup();
+
+        assert_eq!(
+            run_rfc9535("$[?@.a && @.b || @.c]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(5, "a".len())),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(12, "b".len())),
+                ParseToken::Filter(FilterToken::And),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(19, "c".len())),
+                ParseToken::Filter(FilterToken::Or),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run_rfc9535("$ ['a'] .b"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(3, "'a'".len())),
+                ParseToken::ArrayEof,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(9, "b".len()))
+            ])
+        );
+
+        assert!(run_rfc9535("$.00").is_err());
+        assert!(run_rfc9535("$[?@.a == TRUE]").is_err());
+        assert!(run_rfc9535("$[?@.* == 1]").is_err());
+        assert!(run_rfc9535("$.a]").is_err());
+    }
+
+    #[test]
+    fn parse_regex() {
+        setup();
+
+        assert_eq!(
+            run(r#"$[?(@.a =~ /^a\/b/i)]"#),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Regex(StrRange::new(11, r#"/^a\/b/i"#.len())),
+                ParseToken::Filter(FilterToken::Matches),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(@ =~ /[/)]").is_err()); // invalid pattern
+        assert!(run("$[?(@ =~ /a/q)]").is_err()); // unknown flag
+        assert!(run("$[?(@ =~ /a/ii)]").is_err());
+        assert!(run("$[?(@ =~ /a)]").is_err());
+        assert!(run("$[?(@ =~ 'a')]").is_err());
+        assert!(run("$[?(@ =~ /(a)\\1/)]").is_err()); // no backreferences
+        assert!(run("$[?(@ =~ /a{1000}{1000}/)]").is_err()); // size limit
+        assert!(run_rfc9535("$[?@ =~ /a/]").is_err());
+    }
+
+    #[test]
+    fn parse_membership() {
+        setup();
+
+        assert_eq!(
+            run("$[?(@.a in ['b', 1, [true]])]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Literal(json!(["b", 1, [true]])),
+                ParseToken::Filter(FilterToken::In),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@.a anyof $.b)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(16, "b".len())),
+                ParseToken::Filter(FilterToken::AnyOf),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@ noneof [])]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::Literal(json!([])),
+                ParseToken::Filter(FilterToken::NoneOf),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(@ nin ['a',])]").is_err());
+        assert!(run("$[?(@ subsetof 'a')]").is_err());
+        assert!(run("$[?(@ in [a])]").is_err());
+        assert!(run("$[?(@ in [1 2])]").is_err());
+        assert!(run("$[?(@ in)]").is_err());
+        assert!(run_rfc9535("$[?@ in ['a']]").is_err());
+    }
+
+    #[test]
+    fn parse_not() {
+        setup();
+
+        assert_eq!(
+            run("$[?(!@.a)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(7, "a".len())),
+                ParseToken::Filter(FilterToken::Not),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run_rfc9535("$[?!(@ == 1) && @]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::Number(1.into()),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::Filter(FilterToken::Not),
+                ParseToken::Relative,
+                ParseToken::Filter(FilterToken::And),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        // `&&` binds tighter than `||` in the default grammar too
+        assert_eq!(
+            run("$[?(@.a || @.b && @.c)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(13, "b".len())),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(20, "c".len())),
+                ParseToken::Filter(FilterToken::And),
+                ParseToken::Filter(FilterToken::Or),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(! (@.a))]").is_ok());
+        assert!(run_rfc9535("$[?!match(@, 'a')]").is_ok());
+        assert!(run("$[?(!@.a == 1)]").is_err());
+        assert!(run("$[?(!!@.a)]").is_err());
+        assert!(run("$[?(!)]").is_err());
+        assert!(run("$[?(@.a !)]").is_err());
+        assert!(run_rfc9535("$[?!length(@) == 1]").is_err());
+    }
+
+    #[test]
+    fn parse_json_literal() {
+        setup();
+
+        assert_eq!(
+            run(r#"$[?(@.a == [1, {"b": 'c', "d": [null]}])]"#),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Literal(json!([1, {"b": "c", "d": [null]}])),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?({ } != @)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Literal(json!({})),
+                ParseToken::Relative,
+                ParseToken::Filter(FilterToken::NotEqual),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(@ == {a: 1})]").is_err());
+        assert!(run("$[?(@ == {'a' 1})]").is_err());
+        assert!(run("$[?(@ == {'a': 1,})]").is_err());
+        assert!(run("$[?(@ == {'a': 1)]").is_err());
+        assert!(run("$[?([1])]").is_err());
+        assert!(run_rfc9535("$[?@ == [1]]").is_err());
+        assert!(run_rfc9535("$[?@ == {}]").is_err());
+
+        let error = |path| PathParser::compile(path).err();
+        assert_eq!(error("$.p{q}"), Some(TokenError::Position(3)));
+        assert_eq!(error("$.p}q"), Some(TokenError::Position(3)));
+        assert_eq!(error("$[?(@.p{q} == 1)]"), Some(TokenError::Position(7)));
+        assert_eq!(error("$['p{q}']"), None);
+    }
+
+    #[test]
+    fn parse_null() {
+        setup();
+
+        assert_eq!(
+            run("$[?(@.a == null)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Null,
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@ in [null])]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::Literal(json!([null])),
+                ParseToken::Filter(FilterToken::In),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run_rfc9535("$[?null != @.a]").is_ok());
+        assert!(run("$[?(@.a == NULL)]").is_err());
+        assert!(run_rfc9535("$[?@.a == Null]").is_err());
+        assert!(run_rfc9535("$[?null]").is_err());
+    }
+
+    #[test]
+    fn parse_arithmetic() {
+        setup();
+
+        assert_eq!(
+            run("$[?(@.a + 2 * 3 > 1)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Number(2.into()),
+                ParseToken::Number(3.into()),
+                ParseToken::Arithmetic(ArithmeticToken::Multiply),
+                ParseToken::Arithmetic(ArithmeticToken::Add),
+                ParseToken::Number(1.into()),
+                ParseToken::Filter(FilterToken::Greater),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?((1 - @.a) % 2 == 0)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Number(1.into()),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(11, "a".len())),
+                ParseToken::Arithmetic(ArithmeticToken::Subtract),
+                ParseToken::Number(2.into()),
+                ParseToken::Arithmetic(ArithmeticToken::Remainder),
+                ParseToken::Number(0.into()),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(@.a == 6 / @.b - 1)]").is_ok());
+        assert!(run("$[?((@.a * 2) == 6 && (@.b))]").is_ok());
+        assert!(run("$[?(@.a * 2)]").is_err());
+        assert!(run("$[?(@.a + == 1)]").is_err());
+        assert!(run("$[?(@.a -1 == 6)]").is_err()); // `-1` is a number
+
+        // an operator needs blank space around it after a dot-notation name
+        let error = |path| PathParser::compile(path).err();
+        assert_eq!(error("$[?(@.x-1 == 0)]"), Some(TokenError::Position(7)));
+        assert_eq!(error("$[?(@..x+1 == 0)]"), Some(TokenError::Position(8)));
+        assert_eq!(error("$[?($.x/2 == @.y)]"), Some(TokenError::Position(7)));
+        assert_eq!(error("$[?(@.x- @.y == 0)]"), Some(TokenError::Position(7)));
+        assert!(run("$[?(@.x - 1 == 0)]").is_ok());
+        assert!(run("$[?(@['x-1'] == 0)]").is_ok());
+        assert!(run("$[?(@.first-name == 'a')]").is_ok());
+        assert!(run("$.x-1").is_ok());
+        assert!(run("$[?((@.a + 1 == 2)]").is_err());
+        assert!(run_rfc9535("$[?@.a * 2 == 1]").is_err());
+    }
+
+    #[test]
+    fn parse_function() {
+        setup();
+
+        assert_eq!(
+            run_rfc9535("$[?match(@.a, 'b')]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(11, "a".len())),
+                ParseToken::Key(StrRange::new(14, "'b'".len())),
+                ParseToken::Function(FunctionToken::Match),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(length(@) > count($.*))]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::Function(FunctionToken::Length),
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::All,
+                ParseToken::Function(FunctionToken::Count),
+                ParseToken::Filter(FilterToken::Greater),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(length(@))]").is_err());
+        assert!(run("$[?(match(@, 'a') == true)]").is_err());
+        assert!(run("$[?(count(@.a, @.b) == 1)]").is_err());
+        assert!(run("$[?(unknown(@) == 1)]").is_err());
+    }
+
+    #[test]
+    fn parse_custom_function() {
+        setup();
+
+        let mut functions = FunctionRegistry::new();
+        functions
+            .register_value("clamp", &[FunctionType::Value, FunctionType::Value, FunctionType::Value], |_| None).unwrap()
+            .register_logical("any", &[FunctionType::Nodes], |_| true).unwrap();
+        let functions = Rc::new(functions);
+        let run = |input| -> Result<Vec<ParseToken>, TokenError> {
+            let parser = PathParser::compile_with_functions(input, ParserMode::Rfc9535, functions.clone())?;
+            let mut handler = NodeVisitorTestImpl::new(input, ParserMode::Rfc9535);
+            parser.parse(&mut handler).unwrap();
+            Ok(handler.stack)
+        };
+        let clamp = FunctionToken::Custom {
+            name: "clamp".to_string(),
+            parameters: vec![FunctionType::Value, FunctionType::Value, FunctionType::Value],
+            result: FunctionType::Value,
+        };
+
+        assert_eq!(
+            run("$[?clamp(@.a, 0, 10) == 10]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(11, "a".len())),
+                ParseToken::Number(0.into()),
+                ParseToken::Number(10.into()),
+                ParseToken::Function(clamp),
+                ParseToken::Number(10.into()),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?any(@..a)]").is_ok());
+        assert_eq!(run("$[?clamp(@.a, 0) == 1]"), Err(TokenError::Position(16)));
+        assert!(run("$[?clamp(@.a, 0, 1)]").is_err());
+        assert_eq!(run("$[?any(1)]"), Err(TokenError::Position(9)));
+        assert_eq!(run("$[?any(@.a) == true]"), Err(TokenError::Position(14)));
+        assert_eq!(run("$[?unknown(@.a)]"), Err(TokenError::Position(3)));
+
+        // a path compiled without the registry does not know the functions
+        assert!(PathParser::compile("$[?(any(@.a))]").is_err());
+    }
+
+    #[test]
+    fn parse_union() {
+        setup();
+
+        assert_eq!(
+            run("$['a', 0, 1:2, ?(@.b)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Selectors,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(2, "'a'".len())),
+                ParseToken::ArrayEof,
+                ParseToken::Array,
+                ParseToken::Number(0.into()),
+                ParseToken::ArrayEof,
+                ParseToken::Array,
+                ParseToken::Range(Some(1), Some(2), None),
+                ParseToken::ArrayEof,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(19, "b".len())),
+                ParseToken::ArrayEof,
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run_rfc9535("$[*, ?@]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Selectors,
+                ParseToken::Array,
+                ParseToken::All,
+                ParseToken::ArrayEof,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::ArrayEof,
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[0 , 1]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Union(vec![0, 1]),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[0, ]").is_err());
+        assert!(run("$['a', 0,]").is_err());
+        assert!(run_rfc9535("$[0, 01]").is_err());
+    }
+
+    #[test]
+    fn parse_key_pattern() {
+        setup();
+
+        assert_eq!(
+            run("$.a[~'b_*']"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "a".len())),
+                ParseToken::Array,
+                ParseToken::KeyPattern(StrRange::new(5, "'b_*'".len())),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$..[/^b/i, 'c']"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Leaves,
+                ParseToken::Array,
+                ParseToken::Selectors,
+                ParseToken::Array,
+                ParseToken::KeyPattern(StrRange::new(4, "/^b/i".len())),
+                ParseToken::ArrayEof,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(11, "'c'".len())),
+                ParseToken::ArrayEof,
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$['b_*']"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(2, "'b_*'".len())),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run(r#"$['b\*']"#),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(2, r#"'b\*'"#.len())),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[/(/]").is_err());
+        assert!(run("$[/a/g]").is_err());
+        assert!(run_rfc9535("$[/a/]").is_err());
+        assert!(run_rfc9535("$[~'b_*']").is_err());
+        assert!(run("$[~b]").is_err());
+        assert_eq!(
+            run_rfc9535("$['b_*']"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Key(StrRange::new(2, "'b_*'".len())),
+                ParseToken::ArrayEof
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_property_name() {
+        setup();
+
+        assert_eq!(
+            run("$.a.*~"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "a".len())),
+                ParseToken::In,
+                ParseToken::All,
+                ParseToken::PropertyName
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@.*~ == 'a')]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::All,
+                ParseToken::PropertyName,
+                ParseToken::Key(StrRange::new(12, "'a'".len())),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run_rfc9535("$.a~").is_err());
+        assert!(run_rfc9535("$[?@.*~ == 'a']").is_err());
+
+        // nothing may follow `~`, not even the rest of a dot-notation name
+        let error = |path| PathParser::compile(path).err();
+        assert_eq!(error("$.a~.b"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.a~~"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.a~[0]"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.x~y"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.a~ b"), Some(TokenError::Position(5)));
+        assert_eq!(error("$[?(@.*~b == 'a')]"), Some(TokenError::Position(8)));
+        assert_eq!(error("$.a~ "), None);
+        assert_eq!(error("$[?(@.*~)]"), None);
+        assert!(run("$['x~y']").is_ok());
+    }
+
+    #[test]
+    fn parse_parent() {
+        setup();
+
+        assert_eq!(
+            run("$.a[0]^^.b"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "a".len())),
+                ParseToken::Array,
+                ParseToken::Number(0.into()),
+                ParseToken::ArrayEof,
+                ParseToken::Parent,
+                ParseToken::Parent,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(9, "b".len()))
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@.a^ == 1)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Parent,
+                ParseToken::Number(1.into()),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$..^").is_err());
+        assert!(run_rfc9535("$.a^").is_err());
+
+        // a name with `^` in it must be quoted, and no other input may follow `^`
+        let error = |path| PathParser::compile(path).err();
+        assert_eq!(error("$.a^b"), Some(TokenError::Position(4)));
+        assert_eq!(error("$.a^ junk"), Some(TokenError::Position(5)));
+        assert_eq!(error("$.a^'b'"), Some(TokenError::Position(4)));
+        assert_eq!(error("$[?(@^b == 1)]"), Some(TokenError::Position(6)));
+        assert_eq!(error("$.a^ "), None);
+        assert_eq!(error("$[?(@.a^ == 1)]"), None);
+        assert!(run("$['a^b']").is_ok());
+    }
+
+    #[test]
+    fn parse_context() {
+        setup();
+
+        assert_eq!(
+            run("$[?(@property != @parentProperty)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Context(ContextToken::Property),
+                ParseToken::Context(ContextToken::ParentProperty),
+                ParseToken::Filter(FilterToken::NotEqual),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@path == @root.a)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Context(ContextToken::Path),
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(19, "a".len())),
+                ParseToken::Filter(FilterToken::Equal),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        assert!(run("$[?(@property)]").is_err());
+        assert!(run("$[?(@name == 1)]").is_err());
+        assert!(run_rfc9535("$[?@property == 'a']").is_err());
+    }
+
+    #[test]
+    fn parse_aggregate() {
+        setup();
+
+        assert_eq!(
+            run("$.a[*].b.sum()"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "a".len())),
+                ParseToken::Array,
+                ParseToken::All,
+                ParseToken::ArrayEof,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(7, "b".len())),
+                ParseToken::Aggregate(AggregateToken::Sum)
+            ])
+        );
+
+        assert_eq!(
+            run("$[?(@.a.length( ) > 1)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Aggregate(AggregateToken::Length),
+                ParseToken::Number(1.into()),
+                ParseToken::Filter(FilterToken::Greater),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        // `length` is still a member name without the parentheses
+        assert_eq!(
+            run("$.length"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(2, "length".len()))
+            ])
+        );
+
+        assert_eq!(PathParser::compile("$.a.median()").err(), Some(TokenError::Position(4)));
+        assert_eq!(PathParser::compile("$.a.sum().b").err(), Some(TokenError::Position(9)));
+        assert_eq!(PathParser::compile("$.a.sum()[0]").err(), Some(TokenError::Position(9)));
+        assert!(run("$.a.sum(1)").is_err());
+        assert!(run_rfc9535("$.a.sum()").is_err());
+    }
+
+    #[test]
+    fn parse_parameter() {
+        setup();
+
+        assert_eq!(
+            run("$[?(@.a > $min_a && @.b in $b)]"),
+            Ok(vec![
+                ParseToken::Absolute,
+                ParseToken::Array,
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(6, "a".len())),
+                ParseToken::Parameter(StrRange::new(11, "min_a".len())),
+                ParseToken::Filter(FilterToken::Greater),
+                ParseToken::Relative,
+                ParseToken::In,
+                ParseToken::Key(StrRange::new(22, "b".len())),
+                ParseToken::Parameter(StrRange::new(28, "b".len())),
+                ParseToken::Filter(FilterToken::In),
+                ParseToken::Filter(FilterToken::And),
+                ParseToken::ArrayEof
+            ])
+        );
+
+        let parser = PathParser::compile("$[?(@.a == $b || @.c == $a || @.d == $b)]").unwrap();
+        assert_eq!(parser.parameters(), &["b", "a"]);
+        assert!(PathParser::compile("$.a").unwrap().parameters().is_empty());
+
+        assert_eq!(PathParser::compile("$[?(@.a == $1)]").err(), Some(TokenError::Position(11)));
+        assert_eq!(PathParser::compile("$[?(@.a == $a-b)]").err(), Some(TokenError::Position(11)));
+        assert!(run("$[?(@.a == $.b)]").is_ok());
+        assert!(run("$.$ref").is_ok());
+        assert!(run_rfc9535("$[?@.a == $b]").is_err());
+    }
 }
@@ -10,6 +10,8 @@ const CH_LARRAY: char = '[';
 const CH_RARRAY: char = ']';
 const CH_LPAREN: char = '(';
 const CH_RPAREN: char = ')';
+const CH_LBRACE: char = '{';
+const CH_RBRACE: char = '}';
 const CH_AT: char = '@';
 const CH_QUESTION: char = '?';
 const CH_COMMA: char = ',';
@@ -22,6 +24,10 @@ const CH_GREATER: char = '>';
 const CH_EXCLAMATION: char = '!';
 const CH_SINGLE_QUOTE: char = '\'';
 const CH_DOUBLE_QUOTE: char = '"';
+const CH_TILDE: char = '~';
+const CH_CARET: char = '^';
+const CH_SLASH: char = '/';
+const CH_BACKSLASH: char = '\\';
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenError {
@@ -29,6 +35,67 @@ pub enum TokenError {
     Position(usize),
 }
 
+// RFC 9535 2.1.1: blank space is these four only, any other white space belongs to a name
+pub fn is_blank(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+// RFC 9535 2.5.1.1: name-first = ALPHA / "_" / %x80-D7FF / %xE000-10FFFF
+fn is_name_first(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+/// The offset of the first character of `name` that a member name in dot notation,
+/// name-first *(name-first / DIGIT), can not hold; such a name must be bracket-quoted.
+pub fn find_invalid_name_char(name: &str) -> Option<usize> {
+    name.char_indices()
+        .find(|(i, c)| !is_name_first(*c) && (*i == 0 || !c.is_ascii_digit()))
+        .map(|(i, _)| i)
+}
+
+/// Decodes the escapes of a quoted string given without its quotes: those of JSON,
+/// `\uXXXX` surrogate pairs included, and `\'`. Any other escaped character stands for itself.
+pub fn unescape(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != CH_BACKSLASH {
+            ret.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('b') => ret.push('\u{8}'),
+            Some('f') => ret.push('\u{c}'),
+            Some('n') => ret.push('\n'),
+            Some('r') => ret.push('\r'),
+            Some('t') => ret.push('\t'),
+            Some('u') => {
+                let mut code = to_hex_code(&mut chars);
+                if (0xD800..0xDC00).contains(&code) {
+                    let mut rest = chars.clone();
+                    if let (Some(CH_BACKSLASH), Some('u')) = (rest.next(), rest.next()) {
+                        let low = to_hex_code(&mut rest);
+                        if (0xDC00..0xE000).contains(&low) {
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            chars = rest;
+                        }
+                    }
+                }
+                ret.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            Some(c) => ret.push(c),
+            None => {}
+        }
+    }
+    ret
+}
+
+fn to_hex_code(chars: &mut std::str::Chars) -> u32 {
+    let hex: String = chars.by_ref().take(4).collect();
+    u32::from_str_radix(&hex, 16).unwrap_or(0xFFFD)
+}
+
 fn to_token_error(read_err: ReaderError) -> TokenError {
     match read_err {
         ReaderError::Eof => TokenError::Eof,
@@ -38,6 +105,8 @@ fn to_token_error(read_err: ReaderError) -> TokenError {
 #[derive(Clone, Debug)]
 pub(super) struct Tokenizer<'a> {
     input: StrReader<'a>,
+    // a `/` right after `=~`, `[` or `,` opens a regex literal
+    is_regex_expected: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -45,6 +114,7 @@ impl<'a> Tokenizer<'a> {
         trace!("input: {}", input);
         Tokenizer {
             input: StrReader::new(input),
+            is_regex_expected: false,
         }
     }
 
@@ -66,8 +136,10 @@ impl<'a> Tokenizer<'a> {
             | &CH_AMPERSAND
             | &CH_PIPE
             | &CH_EXCLAMATION
+            | &CH_TILDE
+            | &CH_CARET
             => false,
-            _ => !c.is_whitespace(),
+            _ => !is_blank(*c),
         };
         let read = self.input.take_while(fun).map_err(to_token_error)?;
         if read.offset == 0 {
@@ -77,18 +149,40 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    // ends at the first unescaped `ch`; a `\u` escape takes four hex digits and a surrogate pair both halves
     fn quote(&mut self, ch: char) -> Result<StrRange, TokenError> {
-        let span = self.input.take_while(|c| *c != ch).map_err(to_token_error)?;
-        let val = self.input.read(&span);
-        if let Some('\\') = val.chars().last() {
-            self.input.next_char().map_err(to_token_error)?;
-            let remain_span = self.input.take_while(|c| *c != ch).map_err(to_token_error)?;
-            self.input.next_char().map_err(to_token_error)?;
-            Ok(StrRange::new(span.pos, remain_span.offset))
-        } else {
-            self.input.next_char().map_err(to_token_error)?;
-            Ok(span)
+        let pos = self.current_pos();
+        let mut is_high_surrogate = false;
+        loop {
+            let (span, c) = self.input.next_char().map_err(to_token_error)?;
+            let code = match c {
+                CH_BACKSLASH => match self.input.next_char().map_err(to_token_error)? {
+                    (_, 'u') => Some(self.hex_code()?),
+                    _ => None,
+                },
+                _ if c == ch && !is_high_surrogate => return Ok(StrRange::new(pos, span.pos - pos)),
+                _ => None,
+            };
+            is_high_surrogate = match code {
+                Some(0xDC00..=0xDFFF) if is_high_surrogate => false,
+                _ if is_high_surrogate => return Err(TokenError::Position(span.pos)),
+                Some(0xD800..=0xDBFF) => true,
+                Some(0xDC00..=0xDFFF) => return Err(TokenError::Position(span.pos)),
+                _ => false,
+            };
+        }
+    }
+
+    fn hex_code(&mut self) -> Result<u32, TokenError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let (span, c) = self.input.next_char().map_err(to_token_error)?;
+            match c.to_digit(16) {
+                Some(digit) => code = code * 16 + digit,
+                None => return Err(TokenError::Position(span.pos)),
+            }
         }
+        Ok(code)
     }
 
     fn single_quote(&mut self, ch: char) -> Result<Token, TokenError> {
@@ -106,6 +200,10 @@ impl<'a> Tokenizer<'a> {
                 self.input.next_char().map_err(to_token_error)?;
                 Ok(Token::Equal(span))
             }
+            CH_TILDE => {
+                self.input.next_char().map_err(to_token_error)?;
+                Ok(Token::Matches(span))
+            }
             _ => Err(TokenError::Position(span.pos)),
         }
     }
@@ -117,7 +215,7 @@ impl<'a> Tokenizer<'a> {
                 self.input.next_char().map_err(to_token_error)?;
                 Ok(Token::NotEqual(span))
             }
-            _ => Err(TokenError::Position(span.pos)),
+            _ => Ok(Token::Not(span)),
         }
     }
 
@@ -165,10 +263,39 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    fn regex(&mut self, span: StrRange) -> Result<Token, TokenError> {
+        let mut is_escaped = false;
+        loop {
+            let (_, ch) = self.input.next_char().map_err(to_token_error)?;
+            match ch {
+                _ if is_escaped => is_escaped = false,
+                CH_BACKSLASH => is_escaped = true,
+                CH_SLASH => break,
+                _ => {}
+            }
+        }
+        let flags = self.input.take_while(char::is_ascii_alphabetic).map_err(to_token_error)?;
+        Ok(Token::Regex(StrRange::new(span.pos, flags.pos + flags.offset - span.pos)))
+    }
+
+    // @property, @parentProperty, @path and @root; any other name after '@' is an error
+    fn at(&mut self, span: StrRange) -> Result<Token, TokenError> {
+        let name = self.input.take_while(char::is_ascii_alphabetic).map_err(to_token_error)?;
+        if name.offset == 0 {
+            return Ok(Token::At(span));
+        }
+        match self.input.read(&name) {
+            "property" | "parentProperty" | "path" | "root" => {
+                Ok(Token::Context(StrRange::new(span.pos, span.offset + name.offset)))
+            }
+            _ => Err(TokenError::Position(name.pos)),
+        }
+    }
+
     fn whitespace(&mut self) -> Result<Token, TokenError> {
         let span = self
             .input
-            .take_while(|c| c.is_whitespace())
+            .take_while(|c| is_blank(*c))
             .map_err(to_token_error)?;
         Ok(Token::Whitespace(span))
     }
@@ -182,6 +309,8 @@ impl<'a> Tokenizer<'a> {
             | &CH_RARRAY
             | &CH_LPAREN
             | &CH_RPAREN
+            | &CH_LBRACE
+            | &CH_RBRACE
             | &CH_AT
             | &CH_QUESTION
             | &CH_COMMA
@@ -192,8 +321,10 @@ impl<'a> Tokenizer<'a> {
             | &CH_AMPERSAND
             | &CH_PIPE
             | &CH_EXCLAMATION
+            | &CH_TILDE
+            | &CH_CARET
             => false,
-            _ => !c.is_whitespace(),
+            _ => !is_blank(*c),
         };
         let span = self.input.take_while(fun).map_err(to_token_error)?;
         Ok(Token::Key(span))
@@ -208,7 +339,9 @@ impl<'a> Tokenizer<'a> {
             CH_RARRAY => Ok(Token::CloseArray(span)),
             CH_LPAREN => Ok(Token::OpenParenthesis(span)),
             CH_RPAREN => Ok(Token::CloseParenthesis(span)),
-            CH_AT => Ok(Token::At(span)),
+            CH_LBRACE => Ok(Token::OpenBrace(span)),
+            CH_RBRACE => Ok(Token::CloseBrace(span)),
+            CH_AT => self.at(span),
             CH_QUESTION => Ok(Token::Question(span)),
             CH_COMMA => Ok(Token::Comma(span)),
             CH_SEMICOLON => Ok(Token::Split(span)),
@@ -220,17 +353,26 @@ impl<'a> Tokenizer<'a> {
             CH_AMPERSAND => self.and(span),
             CH_PIPE => self.or(span),
             CH_EXCLAMATION => self.not_equal(span),
-            _ if ch.is_whitespace() => self.whitespace(),
+            CH_TILDE => Ok(Token::Tilde(span)),
+            CH_CARET => Ok(Token::Caret(span)),
+            _ if is_blank(ch) => self.whitespace(),
             _ => self.other(),
         }
     }
 
     pub fn next_token(&mut self) -> Result<Token, TokenError> {
         let (span, ch) = self.input.next_char().map_err(to_token_error)?;
-        match self.read_token(span, ch) {
-            Ok(t) => Ok(t),
-            Err(e) => Err(e),
-        }
+        let token = if self.is_regex_expected && ch == CH_SLASH {
+            self.regex(span)
+        } else {
+            self.read_token(span, ch)
+        };
+        self.is_regex_expected = match token {
+            Ok(Token::Matches(_)) | Ok(Token::OpenArray(_)) | Ok(Token::Comma(_)) => true,
+            Ok(Token::Whitespace(_)) => self.is_regex_expected,
+            _ => false,
+        };
+        token
     }
 
     fn current_pos(&self) -> usize {
@@ -246,8 +388,8 @@ impl<'a> Tokenizer<'a> {
 pub(super) struct TokenReader<'a> {
     tokenizer: Tokenizer<'a>,
     curr_pos: usize,
-    err: Option<TokenError>,
     peeked: Option<Result<Token, TokenError>>,
+    peeked_pos: usize,
 }
 
 impl<'a> TokenReader<'a> {
@@ -255,8 +397,8 @@ impl<'a> TokenReader<'a> {
         TokenReader {
             tokenizer: Tokenizer::new(input),
             curr_pos: 0,
-            err: None,
             peeked: None,
+            peeked_pos: 0,
         }
     }
 
@@ -264,7 +406,14 @@ impl<'a> TokenReader<'a> {
         self.tokenizer.read_span(str_range)
     }
 
+    pub fn origin_str(&self) -> &'a str {
+        self.tokenizer.input.origin_str()
+    }
+
     pub fn peek_token(&mut self) -> Result<&Token, &TokenError> {
+        if self.peeked.is_none() {
+            self.peeked_pos = self.curr_pos;
+        }
         let tokenizer = &mut self.tokenizer;
         let prev_pos = self.curr_pos;
         let peeked = self.peeked.get_or_insert_with(|| {
@@ -297,6 +446,11 @@ impl<'a> TokenReader<'a> {
         }
     }
 
+    // the start of the peeked token, where `to_error` points after it
+    pub fn to_peeked_error(&self) -> TokenError {
+        TokenError::Position(self.peeked_pos)
+    }
+
     pub fn to_error(&self) -> TokenError {
         let path = self.tokenizer.input.origin_str();
         let curr_pos = self.curr_pos;
@@ -568,6 +722,148 @@ mod tokenizer_tests {
             ),
         );
 
+        run(
+            r#"?(@ =~ /a\/[)]/i)"#,
+            (
+                vec![
+                    Token::Question(StrRange::new(0, 1)),
+                    Token::OpenParenthesis(StrRange::new(1, 1)),
+                    Token::At(StrRange::new(2, 1)),
+                    Token::Whitespace(StrRange::new(3, 1)),
+                    Token::Matches(StrRange::new(4, 2)),
+                    Token::Whitespace(StrRange::new(6, 1)),
+                    Token::Regex(StrRange::new(7, r#"/a\/[)]/i"#.len())),
+                    Token::CloseParenthesis(StrRange::new(16, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "$['a', /^b/i]",
+            (
+                vec![
+                    Token::Absolute(StrRange::new(0, 1)),
+                    Token::OpenArray(StrRange::new(1, 1)),
+                    Token::SingleQuoted(StrRange::new(2, 3)),
+                    Token::Comma(StrRange::new(5, 1)),
+                    Token::Whitespace(StrRange::new(6, 1)),
+                    Token::Regex(StrRange::new(7, "/^b/i".len())),
+                    Token::CloseArray(StrRange::new(12, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "$.a~",
+            (
+                vec![
+                    Token::Absolute(StrRange::new(0, 1)),
+                    Token::Dot(StrRange::new(1, 1)),
+                    Token::Key(StrRange::new(2, 1)),
+                    Token::Tilde(StrRange::new(3, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "$..a^^",
+            (
+                vec![
+                    Token::Absolute(StrRange::new(0, 1)),
+                    Token::Dot(StrRange::new(1, 1)),
+                    Token::Dot(StrRange::new(2, 1)),
+                    Token::Key(StrRange::new(3, 1)),
+                    Token::Caret(StrRange::new(4, 1)),
+                    Token::Caret(StrRange::new(5, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "$[?(@property==@root.a)]",
+            (
+                vec![
+                    Token::Absolute(StrRange::new(0, 1)),
+                    Token::OpenArray(StrRange::new(1, 1)),
+                    Token::Question(StrRange::new(2, 1)),
+                    Token::OpenParenthesis(StrRange::new(3, 1)),
+                    Token::Context(StrRange::new(4, "@property".len())),
+                    Token::Equal(StrRange::new(13, 2)),
+                    Token::Context(StrRange::new(15, "@root".len())),
+                    Token::Dot(StrRange::new(20, 1)),
+                    Token::Key(StrRange::new(21, 1)),
+                    Token::CloseParenthesis(StrRange::new(22, 1)),
+                    Token::CloseArray(StrRange::new(23, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "@.a@b",
+            (
+                vec![
+                    Token::At(StrRange::new(0, 1)),
+                    Token::Dot(StrRange::new(1, 1)),
+                    Token::Key(StrRange::new(2, 1)),
+                ],
+                Some(TokenError::Position(4)),
+            ),
+        );
+
+        run(
+            "{'a':[1]}",
+            (
+                vec![
+                    Token::OpenBrace(StrRange::new(0, 1)),
+                    Token::SingleQuoted(StrRange::new(1, 3)),
+                    Token::Split(StrRange::new(4, 1)),
+                    Token::OpenArray(StrRange::new(5, 1)),
+                    Token::Key(StrRange::new(6, 1)),
+                    Token::CloseArray(StrRange::new(7, 1)),
+                    Token::CloseBrace(StrRange::new(8, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "?(!@ != 1)",
+            (
+                vec![
+                    Token::Question(StrRange::new(0, 1)),
+                    Token::OpenParenthesis(StrRange::new(1, 1)),
+                    Token::Not(StrRange::new(2, 1)),
+                    Token::At(StrRange::new(3, 1)),
+                    Token::Whitespace(StrRange::new(4, 1)),
+                    Token::NotEqual(StrRange::new(5, 2)),
+                    Token::Whitespace(StrRange::new(7, 1)),
+                    Token::Key(StrRange::new(8, 1)),
+                    Token::CloseParenthesis(StrRange::new(9, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            "?(@ =~ /a",
+            (
+                vec![
+                    Token::Question(StrRange::new(0, 1)),
+                    Token::OpenParenthesis(StrRange::new(1, 1)),
+                    Token::At(StrRange::new(2, 1)),
+                    Token::Whitespace(StrRange::new(3, 1)),
+                    Token::Matches(StrRange::new(4, 2)),
+                    Token::Whitespace(StrRange::new(6, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
         run(
             r#"$["double\"quote"]"#,
             (
@@ -580,5 +876,46 @@ mod tokenizer_tests {
                 Some(TokenError::Eof),
             ),
         );
+
+        run(
+            r#"$['a\\', '\uD83D\uDE00']"#,
+            (
+                vec![
+                    Token::Absolute(StrRange::new(0, 1)),
+                    Token::OpenArray(StrRange::new(1, 1)),
+                    Token::SingleQuoted(StrRange::new(2, r#"'a\\'"#.len())),
+                    Token::Comma(StrRange::new(7, 1)),
+                    Token::Whitespace(StrRange::new(8, 1)),
+                    Token::SingleQuoted(StrRange::new(9, r#"'\uD83D\uDE00'"#.len())),
+                    Token::CloseArray(StrRange::new(23, 1)),
+                ],
+                Some(TokenError::Eof),
+            ),
+        );
+
+        run(
+            r#"$['\u00g1']"#,
+            (
+                vec![Token::Absolute(StrRange::new(0, 1)), Token::OpenArray(StrRange::new(1, 1))],
+                Some(TokenError::Position(7)),
+            ),
+        );
+
+        run(
+            r#"$['\uD83D']"#,
+            (
+                vec![Token::Absolute(StrRange::new(0, 1)), Token::OpenArray(StrRange::new(1, 1))],
+                Some(TokenError::Position(9)),
+            ),
+        );
+    }
+
+    #[test]
+    fn unescape() {
+        assert_eq!(super::unescape(r#"a\'b\"c\\d\/e"#), r#"a'b"c\d/e"#);
+        assert_eq!(super::unescape(r#"\b\f\n\r\t"#), "\u{8}\u{c}\n\r\t");
+        assert_eq!(super::unescape(r#"\u0041\uD83D\uDE00\u00e9"#), "A😀é");
+        assert_eq!(super::unescape(r#"\uD83D"#), "\u{fffd}");
+        assert_eq!(super::unescape(r#"\*\x"#), "*x");
     }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use serde_json::{Number, Value};
+
 use super::str_reader::StrRange;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -13,6 +15,8 @@ pub enum Token {
     Split(StrRange),
     OpenParenthesis(StrRange),
     CloseParenthesis(StrRange),
+    OpenBrace(StrRange),
+    CloseBrace(StrRange),
     Key(StrRange),
     DoubleQuoted(StrRange),
     SingleQuoted(StrRange),
@@ -22,8 +26,14 @@ pub enum Token {
     Little(StrRange),
     LittleOrEqual(StrRange),
     NotEqual(StrRange),
+    Not(StrRange),
     And(StrRange),
     Or(StrRange),
+    Matches(StrRange),
+    Regex(StrRange),
+    Tilde(StrRange),
+    Caret(StrRange),
+    Context(StrRange),
     Whitespace(StrRange),
 }
 
@@ -41,6 +51,8 @@ impl Token {
             Token::Split(_) => matches!(other, Token::Split(_)),
             Token::OpenParenthesis(_) => matches!(other, Token::OpenParenthesis(_)),
             Token::CloseParenthesis(_) => matches!(other, Token::CloseParenthesis(_)),
+            Token::OpenBrace(_) => matches!(other, Token::OpenBrace(_)),
+            Token::CloseBrace(_) => matches!(other, Token::CloseBrace(_)),
             Token::Key(_) => matches!(other, Token::Key(_)),
             Token::DoubleQuoted(_) => matches!(other, Token::DoubleQuoted(_)),
             Token::SingleQuoted(_) => matches!(other, Token::SingleQuoted(_)),
@@ -50,8 +62,14 @@ impl Token {
             Token::Little(_) => matches!(other, Token::Little(_)),
             Token::LittleOrEqual(_) => matches!(other, Token::LittleOrEqual(_)),
             Token::NotEqual(_) => matches!(other, Token::NotEqual(_)),
+            Token::Not(_) => matches!(other, Token::Not(_)),
             Token::And(_) => matches!(other, Token::And(_)),
             Token::Or(_) => matches!(other, Token::Or(_)),
+            Token::Matches(_) => matches!(other, Token::Matches(_)),
+            Token::Regex(_) => matches!(other, Token::Regex(_)),
+            Token::Tilde(_) => matches!(other, Token::Tilde(_)),
+            Token::Caret(_) => matches!(other, Token::Caret(_)),
+            Token::Context(_) => matches!(other, Token::Context(_)),
             Token::Whitespace(_) => matches!(other, Token::Whitespace(_)),
         }
     }
@@ -69,6 +87,8 @@ impl Token {
             Token::Split(_) => Token::Split(new_span),
             Token::OpenParenthesis(_) => Token::OpenParenthesis(new_span),
             Token::CloseParenthesis(_) => Token::CloseParenthesis(new_span),
+            Token::OpenBrace(_) => Token::OpenBrace(new_span),
+            Token::CloseBrace(_) => Token::CloseBrace(new_span),
             Token::Key(_) => Token::Key(new_span),
             Token::DoubleQuoted(_) => Token::DoubleQuoted(new_span),
             Token::SingleQuoted(_) => Token::SingleQuoted(new_span),
@@ -78,8 +98,14 @@ impl Token {
             Token::Little(_) => Token::Little(new_span),
             Token::LittleOrEqual(_) => Token::LittleOrEqual(new_span),
             Token::NotEqual(_) => Token::NotEqual(new_span),
+            Token::Not(_) => Token::Not(new_span),
             Token::And(_) => Token::And(new_span),
             Token::Or(_) => Token::Or(new_span),
+            Token::Matches(_) => Token::Matches(new_span),
+            Token::Regex(_) => Token::Regex(new_span),
+            Token::Tilde(_) => Token::Tilde(new_span),
+            Token::Caret(_) => Token::Caret(new_span),
+            Token::Context(_) => Token::Context(new_span),
             Token::Whitespace(_) => Token::Whitespace(new_span),
         }
     }
@@ -97,24 +123,58 @@ pub enum ParseToken {
     Leaves,
     // '*'
     All,
+    // '~'
+    PropertyName,
+    // '^'
+    Parent,
 
     Key(StrRange),
     Keys(Vec<StrRange>),
+    // [~'metric_*'] or [/^metric_/]
+    KeyPattern(StrRange),
     // []
     Array,
     // 메타토큰
     ArrayEof,
     // ?( filter )
     Filter(FilterToken),
-    // 1 : 2
-    Range(Option<isize>, Option<isize>, Option<usize>),
+    // 1 : 2 : -1
+    Range(Option<isize>, Option<isize>, Option<isize>),
     // 1, 2, 3
     Union(Vec<isize>),
+    // 'a', 0, 1:2, ?(@.b)
+    Selectors,
 
-    Number(f64),
+    Number(Number),
 
     Bool(bool),
 
+    Null,
+
+    // /pattern/flags
+    Regex(StrRange),
+
+    // ['a', 1] or {"a": 1} in a filter
+    Literal(Value),
+
+    // length(@.a)
+    Function(FunctionToken),
+
+    // an argument of a function, chained to the next one
+    Argument,
+
+    // @.a * 2
+    Arithmetic(ArithmeticToken),
+
+    // @property, @parentProperty or @path
+    Context(ContextToken),
+
+    // .sum() at the end of a path
+    Aggregate(AggregateToken),
+
+    // $name in a filter, bound to a value when the path is selected
+    Parameter(StrRange),
+
     Eof,
 }
 
@@ -128,4 +188,121 @@ pub enum FilterToken {
     GreaterOrEqual,
     And,
     Or,
-}
\ No newline at end of file
+    // !
+    Not,
+    // =~
+    Matches,
+    In,
+    Nin,
+    SubsetOf,
+    AnyOf,
+    NoneOf,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ArithmeticToken {
+    // +
+    Add,
+    // -
+    Subtract,
+    // *
+    Multiply,
+    // /
+    Divide,
+    // %
+    Remainder,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ContextToken {
+    // the member name or array index of the filtered node
+    Property,
+    // the member name or array index of its parent
+    ParentProperty,
+    // its normalized path
+    Path,
+}
+
+/// Types of the function extensions (RFC 9535 2.4.1): ValueType, LogicalType and NodesType.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FunctionType {
+    Value,
+    Logical,
+    Nodes,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FunctionToken {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+    // registered in a `FunctionRegistry`
+    Custom {
+        name: String,
+        parameters: Vec<FunctionType>,
+        result: FunctionType,
+    },
+}
+
+impl FunctionToken {
+    pub fn from_name(name: &str) -> Option<FunctionToken> {
+        match name {
+            "length" => Some(FunctionToken::Length),
+            "count" => Some(FunctionToken::Count),
+            "match" => Some(FunctionToken::Match),
+            "search" => Some(FunctionToken::Search),
+            "value" => Some(FunctionToken::Value),
+            _ => None,
+        }
+    }
+
+    pub fn parameters(&self) -> &[FunctionType] {
+        match self {
+            FunctionToken::Length => &[FunctionType::Value],
+            FunctionToken::Count | FunctionToken::Value => &[FunctionType::Nodes],
+            FunctionToken::Match | FunctionToken::Search => &[FunctionType::Value, FunctionType::Value],
+            FunctionToken::Custom { parameters, .. } => parameters,
+        }
+    }
+
+    pub fn result(&self) -> FunctionType {
+        match self {
+            FunctionToken::Length | FunctionToken::Count | FunctionToken::Value => FunctionType::Value,
+            FunctionToken::Match | FunctionToken::Search => FunctionType::Logical,
+            FunctionToken::Custom { result, .. } => *result,
+        }
+    }
+}
+
+/// Functions that end a path and compute one value from its nodes, e.g. `$..price.sum()`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum AggregateToken {
+    Sum,
+    Min,
+    Max,
+    Avg,
+    Stddev,
+    Length,
+    Keys,
+    First,
+    Last,
+}
+
+impl AggregateToken {
+    pub fn from_name(name: &str) -> Option<AggregateToken> {
+        match name {
+            "sum" => Some(AggregateToken::Sum),
+            "min" => Some(AggregateToken::Min),
+            "max" => Some(AggregateToken::Max),
+            "avg" => Some(AggregateToken::Avg),
+            "stddev" => Some(AggregateToken::Stddev),
+            "length" => Some(AggregateToken::Length),
+            "keys" => Some(AggregateToken::Keys),
+            "first" => Some(AggregateToken::First),
+            "last" => Some(AggregateToken::Last),
+            _ => None,
+        }
+    }
+}
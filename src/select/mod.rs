@@ -165,15 +165,9 @@ impl<'a> FilterTerms<'a> {
             let mut visited = HashSet::new();
             for (idx, v) in vec.iter().enumerate() {
                 match v {
-                    Value::Object(map) => {
-                        if map.contains_key(key) {
-                            let ptr = *v as *const Value;
-                            if !visited.contains(&ptr) {
-                                visited.insert(ptr);
-                                tmp.push(v)
-                            }
-                        } else {
-                            not_matched.insert(idx);
+                    Value::Object(map) if map.contains_key(key) => {
+                        if visited.insert(*v as *const Value) {
+                            tmp.push(v)
                         }
                     }
                     Value::Array(vec) => {
@@ -71,14 +71,8 @@ impl<'a> ValueWalker {
                       key: &str,
                       visited: &mut HashSet<*const Value>, ) {
         match v {
-            Value::Object(map) => {
-                if map.contains_key(key) {
-                    let ptr = v as *const Value;
-                    if !visited.contains(&ptr) {
-                        visited.insert(ptr);
-                        tmp.push(v)
-                    }
-                }
+            Value::Object(map) if map.contains_key(key) && visited.insert(v as *const Value) => {
+                tmp.push(v)
             }
             Value::Array(vec) => {
                 for v in vec {
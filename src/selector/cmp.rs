@@ -1,19 +1,87 @@
-use serde_json::Value;
+use std::cmp::Ordering;
+
+use serde_json::{Number, Value};
+
+use super::utils;
 
 pub trait Cmp {
     fn cmp_bool(&self, v1: bool, v2: bool) -> bool;
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool;
+    /// Compares two numbers exactly, integers beyond 2^53 included.
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool;
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool;
 
+    /// Compares `null` with `null`.
+    fn cmp_null(&self) -> bool;
+
+    /// The nodes of `v1` that compare with the nodes of `v2` by value: for `!=` when
+    /// no pair of them is equal, and otherwise when some pair compares.
     fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value>;
 
+    /// Compares two values by RFC 9535 rules, `None` being an empty node list.
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool;
+
+    /// Compares a value with the members of a set, for the membership operators.
+    fn cmp_members(&self, _v1: &Value, _members: &[&Value]) -> bool {
+        self.default()
+    }
+
     fn default(&self) -> bool {
         false
     }
 }
 
+fn eq_value(v1: &Value, v2: &Value) -> bool {
+    match (v1, v2) {
+        (Value::Number(n1), Value::Number(n2)) => utils::cmp_number(n1, n2) == Ordering::Equal,
+        (Value::Array(a1), Value::Array(a2)) => {
+            a1.len() == a2.len() && a1.iter().zip(a2).all(|(v1, v2)| eq_value(v1, v2))
+        }
+        (Value::Object(m1), Value::Object(m2)) => {
+            m1.len() == m2.len() && m1.iter().all(|(k, v1)| m2.get(k).is_some_and(|v2| eq_value(v1, v2)))
+        }
+        _ => v1 == v2,
+    }
+}
+
+// the nodes of `v1` that compare with some node of `v2` by `Cmp::cmp_value`
+fn cmp_json_value<'a, C: Cmp>(cmp_fn: &C, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+    v1.iter()
+        .filter(|a| v2.iter().any(|b| cmp_fn.cmp_value(Some(a), Some(b))))
+        .copied()
+        .collect()
+}
+
+// `cmp_json_value` for the ordering operators, which order numbers and strings only
+fn cmp_json_ordered<'a, C: Cmp>(cmp_fn: &C, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+    v1.iter()
+        .filter(|a| v2.iter().any(|b| match (a, b) {
+            (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_)) => {
+                cmp_fn.cmp_value(Some(a), Some(b))
+            }
+            _ => false,
+        }))
+        .copied()
+        .collect()
+}
+
+// a membership operator by `Cmp::cmp_value`: `v2` is the set, as an array
+fn cmp_value_members<C: Cmp>(cmp_fn: &C, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+    match (v1, v2) {
+        (Some(v1), Some(Value::Array(vec))) => cmp_fn.cmp_members(v1, &vec.iter().collect::<Vec<_>>()),
+        _ => false,
+    }
+}
+
+fn lt_value(v1: &Value, v2: &Value) -> bool {
+    match (v1, v2) {
+        (Value::Number(n1), Value::Number(n2)) => utils::cmp_number(n1, n2) == Ordering::Less,
+        (Value::String(s1), Value::String(s2)) => s1 < s2,
+        _ => false,
+    }
+}
+
 pub struct CmpEq;
 
 impl Cmp for CmpEq {
@@ -21,23 +89,28 @@ impl Cmp for CmpEq {
         v1 == v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        (v1 - v2).abs() == 0_f64
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) == Ordering::Equal
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 == v2
     }
 
+    fn cmp_null(&self) -> bool {
+        true
+    }
+
     fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
-        v1.iter().fold(Vec::new(), |acc, a| {
-            v2.iter().fold(acc, |mut acc, b| {
-                if std::ptr::eq(*a, *b) {
-                    acc.push(*a);
-                }
-                acc
-            })
-        })
+        cmp_json_value(self, v1, v2)
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        match (v1, v2) {
+            (Some(v1), Some(v2)) => eq_value(v1, v2),
+            (None, None) => true,
+            _ => false,
+        }
     }
 }
 
@@ -48,25 +121,27 @@ impl Cmp for CmpNe {
         v1 != v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        (v1 - v2).abs() != 0_f64
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) != Ordering::Equal
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 != v2
     }
 
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
     fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
-        let mut ret = v1.to_vec();
-        for v in v2 {
-            for i in 0..ret.len() {
-                if std::ptr::eq(*v, &*ret[i]) {
-                    ret.remove(i);
-                    break;
-                }
-            }
+        if v2.is_empty() || !CmpEq.cmp_json(v1, v2).is_empty() {
+            return Vec::new();
         }
-        ret
+        v1.to_vec()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        !CmpEq.cmp_value(v1, v2)
     }
 
     fn default(&self) -> bool {
@@ -81,16 +156,24 @@ impl Cmp for CmpGt {
         v1 & !v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        v1 > v2
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) == Ordering::Greater
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 > v2
     }
 
-    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
-        Vec::new()
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+        cmp_json_ordered(self, v1, v2)
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        CmpLt.cmp_value(v2, v1)
     }
 }
 
@@ -101,16 +184,24 @@ impl Cmp for CmpGe {
         v1 >= v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        v1 >= v2
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) != Ordering::Less
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 >= v2
     }
 
-    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
-        Vec::new()
+    fn cmp_null(&self) -> bool {
+        true
+    }
+
+    fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+        cmp_json_ordered(self, v1, v2)
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        CmpGt.cmp_value(v1, v2) || CmpEq.cmp_value(v1, v2)
     }
 }
 
@@ -121,16 +212,27 @@ impl Cmp for CmpLt {
         !v1 & v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        v1 < v2
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) == Ordering::Less
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 < v2
     }
 
-    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
-        Vec::new()
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+        cmp_json_ordered(self, v1, v2)
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        match (v1, v2) {
+            (Some(v1), Some(v2)) => lt_value(v1, v2),
+            _ => false,
+        }
     }
 }
 
@@ -141,16 +243,24 @@ impl Cmp for CmpLe {
         v1 <= v2
     }
 
-    fn cmp_f64(&self, v1: f64, v2: f64) -> bool {
-        v1 <= v2
+    fn cmp_number(&self, v1: &Number, v2: &Number) -> bool {
+        utils::cmp_number(v1, v2) != Ordering::Greater
     }
 
     fn cmp_string(&self, v1: &str, v2: &str) -> bool {
         v1 <= v2
     }
 
-    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
-        Vec::new()
+    fn cmp_null(&self) -> bool {
+        true
+    }
+
+    fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
+        cmp_json_ordered(self, v1, v2)
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        CmpLt.cmp_value(v1, v2) || CmpEq.cmp_value(v1, v2)
     }
 }
 
@@ -161,7 +271,7 @@ impl Cmp for CmpAnd {
         v1 && v2
     }
 
-    fn cmp_f64(&self, _v1: f64, _v2: f64) -> bool {
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
         true
     }
 
@@ -169,9 +279,17 @@ impl Cmp for CmpAnd {
         !v1.is_empty() && !v2.is_empty()
     }
 
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
     fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
         CmpEq.cmp_json(v1, v2)
     }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        v1.is_some() && v2.is_some()
+    }
 }
 
 pub struct CmpOr;
@@ -181,7 +299,7 @@ impl Cmp for CmpOr {
         v1 || v2
     }
 
-    fn cmp_f64(&self, _v1: f64, _v2: f64) -> bool {
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
         true
     }
 
@@ -189,12 +307,16 @@ impl Cmp for CmpOr {
         !v1.is_empty() || !v2.is_empty()
     }
 
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
     fn cmp_json<'a>(&self, v1: &[&'a Value], v2: &[&'a Value]) -> Vec<&'a Value> {
         v2.iter().fold(v1.to_vec(), |mut acc, v| {
             let mut contains = false;
 
-            for ptr in v1.iter().map(|v| *v as *const Value) {
-                if ptr == *v as *const Value {
+            for ptr in v1.iter() {
+                if std::ptr::eq(*ptr, *v) {
                     contains = true;
                     break;
                 }
@@ -207,25 +329,203 @@ impl Cmp for CmpOr {
             acc
         })
     }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        v1.is_some() || v2.is_some()
+    }
+}
+
+pub struct CmpIn;
+
+impl Cmp for CmpIn {
+    fn cmp_bool(&self, _v1: bool, _v2: bool) -> bool {
+        false
+    }
+
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
+        false
+    }
+
+    fn cmp_string(&self, _v1: &str, _v2: &str) -> bool {
+        false
+    }
+
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
+        Vec::new()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        cmp_value_members(self, v1, v2)
+    }
+
+    fn cmp_members(&self, v1: &Value, members: &[&Value]) -> bool {
+        members.iter().any(|v2| eq_value(v1, v2))
+    }
+}
+
+pub struct CmpNin;
+
+impl Cmp for CmpNin {
+    fn cmp_bool(&self, _v1: bool, _v2: bool) -> bool {
+        false
+    }
+
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
+        false
+    }
+
+    fn cmp_string(&self, _v1: &str, _v2: &str) -> bool {
+        false
+    }
+
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
+        Vec::new()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        cmp_value_members(self, v1, v2)
+    }
+
+    fn cmp_members(&self, v1: &Value, members: &[&Value]) -> bool {
+        !CmpIn.cmp_members(v1, members)
+    }
+}
+
+pub struct CmpSubsetOf;
+
+impl Cmp for CmpSubsetOf {
+    fn cmp_bool(&self, _v1: bool, _v2: bool) -> bool {
+        false
+    }
+
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
+        false
+    }
+
+    fn cmp_string(&self, _v1: &str, _v2: &str) -> bool {
+        false
+    }
+
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
+        Vec::new()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        cmp_value_members(self, v1, v2)
+    }
+
+    fn cmp_members(&self, v1: &Value, members: &[&Value]) -> bool {
+        match v1 {
+            Value::Array(vec) => vec.iter().all(|v| CmpIn.cmp_members(v, members)),
+            _ => false,
+        }
+    }
+}
+
+pub struct CmpAnyOf;
+
+impl Cmp for CmpAnyOf {
+    fn cmp_bool(&self, _v1: bool, _v2: bool) -> bool {
+        false
+    }
+
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
+        false
+    }
+
+    fn cmp_string(&self, _v1: &str, _v2: &str) -> bool {
+        false
+    }
+
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
+        Vec::new()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        cmp_value_members(self, v1, v2)
+    }
+
+    fn cmp_members(&self, v1: &Value, members: &[&Value]) -> bool {
+        match v1 {
+            Value::Array(vec) => vec.iter().any(|v| CmpIn.cmp_members(v, members)),
+            _ => false,
+        }
+    }
+}
+
+pub struct CmpNoneOf;
+
+impl Cmp for CmpNoneOf {
+    fn cmp_bool(&self, _v1: bool, _v2: bool) -> bool {
+        false
+    }
+
+    fn cmp_number(&self, _v1: &Number, _v2: &Number) -> bool {
+        false
+    }
+
+    fn cmp_string(&self, _v1: &str, _v2: &str) -> bool {
+        false
+    }
+
+    fn cmp_null(&self) -> bool {
+        false
+    }
+
+    fn cmp_json<'a>(&self, _: &[&'a Value], _: &[&'a Value]) -> Vec<&'a Value> {
+        Vec::new()
+    }
+
+    fn cmp_value(&self, v1: Option<&Value>, v2: Option<&Value>) -> bool {
+        cmp_value_members(self, v1, v2)
+    }
+
+    fn cmp_members(&self, v1: &Value, members: &[&Value]) -> bool {
+        match v1 {
+            Value::Array(vec) => !vec.iter().any(|v| CmpIn.cmp_members(v, members)),
+            _ => false,
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod cmp_inner_tests {
-    use serde_json::Value;
+    use serde_json::{json, Number, Value};
 
     use selector::cmp::*;
 
+    fn num(f: f64) -> Number {
+        Number::from_f64(f).unwrap()
+    }
+
     #[test]
     fn cmp_eq() {
         let cmp_fn = CmpEq;
         assert!(!cmp_fn.default());
         assert!(!cmp_fn.cmp_bool(true, false));
         assert!(cmp_fn.cmp_bool(true, true));
-        assert!(cmp_fn.cmp_f64(0.1, 0.1));
-        assert!(!cmp_fn.cmp_f64(0.1, 0.2));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.1)));
+        assert!(!cmp_fn.cmp_number(&num(0.1), &num(0.2)));
         assert!(cmp_fn.cmp_string("1", "1"));
         assert!(!cmp_fn.cmp_string("1", "2"));
+        assert!(cmp_fn.cmp_null());
     }
 
     #[test]
@@ -234,10 +534,11 @@ mod cmp_inner_tests {
         assert!(cmp_fn.default());
         assert!(cmp_fn.cmp_bool(true, false));
         assert!(!cmp_fn.cmp_bool(true, true));
-        assert!(!cmp_fn.cmp_f64(0.1, 0.1));
-        assert!(cmp_fn.cmp_f64(0.1, 0.2));
+        assert!(!cmp_fn.cmp_number(&num(0.1), &num(0.1)));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.2)));
         assert!(!cmp_fn.cmp_string("1", "1"));
         assert!(cmp_fn.cmp_string("1", "2"));
+        assert!(!cmp_fn.cmp_null());
     }
 
     #[test]
@@ -246,11 +547,12 @@ mod cmp_inner_tests {
         assert!(!cmp_fn.default());
         assert!(cmp_fn.cmp_bool(true, false));
         assert!(!cmp_fn.cmp_bool(true, true));
-        assert!(cmp_fn.cmp_f64(0.2, 0.1));
-        assert!(!cmp_fn.cmp_f64(0.1, 0.2));
+        assert!(cmp_fn.cmp_number(&num(0.2), &num(0.1)));
+        assert!(!cmp_fn.cmp_number(&num(0.1), &num(0.2)));
         assert!(!cmp_fn.cmp_string("a", "a"));
         assert!(cmp_fn.cmp_string("b", "a"));
         assert!(!cmp_fn.cmp_string("1", "2"));
+        assert!(!cmp_fn.cmp_null());
     }
 
     #[test]
@@ -259,12 +561,13 @@ mod cmp_inner_tests {
         assert!(!cmp_fn.default());
         assert!(cmp_fn.cmp_bool(true, false));
         assert!(cmp_fn.cmp_bool(true, true));
-        assert!(cmp_fn.cmp_f64(0.2, 0.1));
-        assert!(cmp_fn.cmp_f64(0.1, 0.1));
-        assert!(!cmp_fn.cmp_f64(0.1, 0.2));
+        assert!(cmp_fn.cmp_number(&num(0.2), &num(0.1)));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.1)));
+        assert!(!cmp_fn.cmp_number(&num(0.1), &num(0.2)));
         assert!(cmp_fn.cmp_string("1", "1"));
         assert!(cmp_fn.cmp_string("ab", "a"));
         assert!(!cmp_fn.cmp_string("1", "2"));
+        assert!(cmp_fn.cmp_null());
     }
 
     #[test]
@@ -275,12 +578,13 @@ mod cmp_inner_tests {
         assert!(cmp_fn.cmp_bool(false, true));
         assert!(!cmp_fn.cmp_bool(true, true));
         assert!(!cmp_fn.cmp_bool(false, false));
-        assert!(cmp_fn.cmp_f64(0.1, 0.2));
-        assert!(!cmp_fn.cmp_f64(0.1, 0.1));
-        assert!(!cmp_fn.cmp_f64(0.2, 0.1));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.2)));
+        assert!(!cmp_fn.cmp_number(&num(0.1), &num(0.1)));
+        assert!(!cmp_fn.cmp_number(&num(0.2), &num(0.1)));
         assert!(!cmp_fn.cmp_string("a", "a"));
         assert!(cmp_fn.cmp_string("ab", "b"));
         assert!(cmp_fn.cmp_string("1", "2"));
+        assert!(!cmp_fn.cmp_null());
     }
 
     #[test]
@@ -291,13 +595,14 @@ mod cmp_inner_tests {
         assert!(cmp_fn.cmp_bool(false, true));
         assert!(cmp_fn.cmp_bool(true, true));
         assert!(cmp_fn.cmp_bool(false, false));
-        assert!(cmp_fn.cmp_f64(0.1, 0.2));
-        assert!(cmp_fn.cmp_f64(0.1, 0.1));
-        assert!(!cmp_fn.cmp_f64(0.2, 0.1));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.2)));
+        assert!(cmp_fn.cmp_number(&num(0.1), &num(0.1)));
+        assert!(!cmp_fn.cmp_number(&num(0.2), &num(0.1)));
         assert!(cmp_fn.cmp_string("a", "a"));
         assert!(cmp_fn.cmp_string("ab", "b"));
         assert!(!cmp_fn.cmp_string("abd", "abc"));
         assert!(cmp_fn.cmp_string("1", "2"));
+        assert!(cmp_fn.cmp_null());
     }
 
     #[test]
@@ -308,8 +613,9 @@ mod cmp_inner_tests {
         assert!(!cmp_fn.cmp_bool(false, true));
         assert!(cmp_fn.cmp_bool(true, true));
         assert!(!cmp_fn.cmp_bool(false, false));
-        assert!(cmp_fn.cmp_f64(0.0, 0.0));
+        assert!(cmp_fn.cmp_number(&num(0.0), &num(0.0)));
         assert!(cmp_fn.cmp_string("a", "a"));
+        assert!(!cmp_fn.cmp_null());
     }
 
     #[test]
@@ -320,8 +626,32 @@ mod cmp_inner_tests {
         assert!(cmp_fn.cmp_bool(false, true));
         assert!(cmp_fn.cmp_bool(true, true));
         assert!(!cmp_fn.cmp_bool(false, false));
-        assert!(cmp_fn.cmp_f64(0.0, 0.0));
+        assert!(cmp_fn.cmp_number(&num(0.0), &num(0.0)));
         assert!(cmp_fn.cmp_string("a", "a"));
+        assert!(!cmp_fn.cmp_null());
+    }
+
+    #[test]
+    fn cmp_number_exact() {
+        let above = Number::from(9_007_199_254_740_993_i64);
+        let below = Number::from(9_007_199_254_740_992_i64);
+        assert!(!CmpEq.cmp_number(&above, &below));
+        assert!(CmpGt.cmp_number(&above, &below));
+        assert!(CmpLt.cmp_number(&below, &above));
+        assert!(!CmpEq.cmp_number(&above, &num(9_007_199_254_740_992_f64)));
+        assert!(CmpEq.cmp_number(&below, &num(9_007_199_254_740_992_f64)));
+
+        let max = Number::from(u64::MAX);
+        assert!(CmpGt.cmp_number(&max, &Number::from(i64::MAX)));
+        assert!(CmpLt.cmp_number(&max, &num(18_446_744_073_709_551_616_f64)));
+        assert!(CmpLt.cmp_number(&Number::from(-1), &max));
+
+        assert!(CmpEq.cmp_number(&Number::from(1), &num(1.0)));
+        assert!(CmpNe.cmp_number(&Number::from(1), &num(1.5)));
+        assert!(CmpLt.cmp_number(&Number::from(1), &num(1.5)));
+        assert!(CmpGt.cmp_number(&Number::from(-1), &num(-1.5)));
+        assert!(CmpLe.cmp_number(&Number::from(0), &num(-0.0)));
+        assert!(CmpGe.cmp_number(&num(1e300), &max));
     }
 
     #[test]
@@ -335,9 +665,9 @@ mod cmp_inner_tests {
         assert_eq!(CmpEq.cmp_json(&left, &right), left.to_vec());
         assert_eq!(CmpNe.cmp_json(&left, &right), empty);
         assert_eq!(CmpGt.cmp_json(&left, &right), empty);
-        assert_eq!(CmpGe.cmp_json(&left, &right), empty);
+        assert_eq!(CmpGe.cmp_json(&left, &right), vec![&v2]);
         assert_eq!(CmpLt.cmp_json(&left, &right), empty);
-        assert_eq!(CmpLe.cmp_json(&left, &right), empty);
+        assert_eq!(CmpLe.cmp_json(&left, &right), vec![&v2]);
         assert_eq!(CmpAnd.cmp_json(&left, &right), left.to_vec());
         assert_eq!(CmpOr.cmp_json(&left, &right), left.to_vec());
 
@@ -365,5 +695,42 @@ mod cmp_inner_tests {
             CmpOr.cmp_json(&[&Value::Bool(true)], &[&Value::Bool(false)]),
             vec![&Value::Bool(true), &Value::Bool(false)]
         );
+
+        // by value, not by node
+        let (a, b) = (json!({"x": [1, "y"]}), json!({"x": [1.0, "y"]}));
+        assert_eq!(CmpEq.cmp_json(&[&a], &[&b]), vec![&a]);
+        assert_eq!(CmpNe.cmp_json(&[&a], &[&b]), empty);
+        assert_eq!(CmpNe.cmp_json(&[&a], &[]), empty);
+        assert_eq!(CmpGt.cmp_json(&[&json!(2), &json!("b")], &[&json!("a")]), vec![&json!("b")]);
+    }
+
+    #[test]
+    fn cmp_members() {
+        let a = json!("a");
+        let one = json!(1.0);
+        let members = [&json!(1), &json!("a"), &json!([1])];
+
+        assert!(CmpIn.cmp_members(&a, &members));
+        assert!(CmpIn.cmp_members(&one, &members));
+        assert!(CmpIn.cmp_members(&json!([1]), &members));
+        assert!(!CmpIn.cmp_members(&json!("b"), &members));
+        assert!(!CmpNin.cmp_members(&a, &members));
+        assert!(CmpNin.cmp_members(&json!("b"), &members));
+
+        assert!(CmpSubsetOf.cmp_members(&json!(["a", 1]), &members));
+        assert!(CmpSubsetOf.cmp_members(&json!([]), &members));
+        assert!(!CmpSubsetOf.cmp_members(&json!(["a", "b"]), &members));
+        assert!(CmpAnyOf.cmp_members(&json!(["a", "b"]), &members));
+        assert!(!CmpAnyOf.cmp_members(&json!(["b"]), &members));
+        assert!(CmpNoneOf.cmp_members(&json!(["b"]), &members));
+        assert!(!CmpNoneOf.cmp_members(&json!(["a", "b"]), &members));
+        assert!(!CmpSubsetOf.cmp_members(&a, &members));
+        assert!(!CmpAnyOf.cmp_members(&a, &members));
+        assert!(!CmpNoneOf.cmp_members(&a, &members));
+
+        assert!(CmpIn.cmp_value(Some(&a), Some(&json!(["a"]))));
+        assert!(!CmpIn.cmp_value(Some(&a), Some(&a)));
+        assert!(!CmpNin.cmp_value(None, Some(&json!(["a"]))));
+        assert!(!CmpEq.cmp_members(&a, &members));
     }
 }
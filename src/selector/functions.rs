@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::{Regex, RegexBuilder};
+use serde_json::{Number, Value};
+
+use paths::tokens::{AggregateToken, ArithmeticToken};
+
+use super::utils;
+
+// RFC 9535 2.4.4
+pub fn length(v: Option<&Value>) -> Option<Value> {
+    match v {
+        Some(Value::String(s)) => Some(Value::from(s.chars().count())),
+        Some(Value::Array(vec)) => Some(Value::from(vec.len())),
+        Some(Value::Object(map)) => Some(Value::from(map.len())),
+        _ => None,
+    }
+}
+
+// RFC 9535 2.4.5
+pub fn count(nodes: &[&Value]) -> Option<Value> {
+    Some(Value::from(nodes.len()))
+}
+
+// RFC 9535 2.4.8
+pub fn value(nodes: &[&Value]) -> Option<Value> {
+    match nodes {
+        [v] => Some((*v).clone()),
+        _ => None,
+    }
+}
+
+/// An aggregate function ending a path, e.g. `$..price.sum()`. Like Jayway JsonPath,
+/// it reads the items of an array when the path selects a single array, and the
+/// nodes otherwise. Numeric functions skip what is not a number, and `keys` what is
+/// not an object; with nothing left there is no value.
+pub fn aggregate(token: &AggregateToken, nodes: &[&Value]) -> Option<Value> {
+    let items = match nodes {
+        [Value::Array(vec)] => vec.iter().collect(),
+        _ => nodes.to_vec(),
+    };
+    let numbers: Vec<&Number> = items.iter()
+        .filter_map(|v| match v {
+            Value::Number(n) => Some(n),
+            _ => None,
+        })
+        .collect();
+
+    match token {
+        AggregateToken::Sum if numbers.is_empty() => None,
+        AggregateToken::Sum => numbers.iter()
+            .try_fold(Number::from(0), |sum, n| utils::arithmetic(&ArithmeticToken::Add, &sum, n))
+            .map(Value::Number),
+        AggregateToken::Min => numbers.into_iter().min_by(|n1, n2| utils::cmp_number(n1, n2)).cloned().map(Value::Number),
+        AggregateToken::Max => numbers.into_iter().max_by(|n1, n2| utils::cmp_number(n1, n2)).cloned().map(Value::Number),
+        AggregateToken::Avg => mean(&numbers).and_then(Number::from_f64).map(Value::Number),
+        AggregateToken::Stddev => mean(&numbers)
+            .map(|mean| numbers.iter().map(|n| (utils::to_f64(n) - mean).powi(2)).sum::<f64>() / numbers.len() as f64)
+            .and_then(|variance| Number::from_f64(variance.sqrt()))
+            .map(Value::Number),
+        AggregateToken::Length => match nodes {
+            [Value::Array(vec)] => Some(Value::from(vec.len())),
+            [Value::Object(map)] => Some(Value::from(map.len())),
+            _ => Some(Value::from(nodes.len())),
+        },
+        AggregateToken::Keys => keys(&items),
+        AggregateToken::First => items.first().map(|v| (*v).clone()),
+        AggregateToken::Last => items.last().map(|v| (*v).clone()),
+    }
+}
+
+// the member names of the objects, each once in the order it first appears
+fn keys(items: &[&Value]) -> Option<Value> {
+    let mut names = HashSet::new();
+    let mut keys = Vec::new();
+    let mut has_object = false;
+    for map in items.iter().filter_map(|v| v.as_object()) {
+        has_object = true;
+        for key in map.keys() {
+            if names.insert(key) {
+                keys.push(Value::String(key.clone()));
+            }
+        }
+    }
+    if has_object {
+        Some(Value::Array(keys))
+    } else {
+        None
+    }
+}
+
+fn mean(numbers: &[&Number]) -> Option<f64> {
+    if numbers.is_empty() {
+        return None;
+    }
+    Some(numbers.iter().map(|n| utils::to_f64(n)).sum::<f64>() / numbers.len() as f64)
+}
+
+//
+// RFC 9535 2.4.6 and 2.4.7: `match` tests the whole string and `search` any substring
+// against an I-Regexp (RFC 9485). A pattern that does not compile matches nothing.
+//
+#[derive(Default)]
+pub struct RegexMatcher {
+    regexes: HashMap<(String, bool), Option<Regex>>,
+}
+
+impl RegexMatcher {
+    pub fn is_match(&mut self, v: Option<&Value>, pattern: Option<&Value>, is_full: bool) -> bool {
+        let (s, pattern) = match (v, pattern) {
+            (Some(Value::String(s)), Some(Value::String(pattern))) => (s, pattern),
+            _ => return false,
+        };
+
+        self.regexes
+            .entry((pattern.clone(), is_full))
+            .or_insert_with(|| to_regex(pattern, is_full))
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(s))
+    }
+}
+
+// RFC 9485 5.3: `.` does not match line ends, and `^` and `$` are ordinary characters
+fn to_regex(pattern: &str, is_full: bool) -> Option<Regex> {
+    let mut translated = String::from(if is_full { r"\A(?:" } else { "(?:" });
+    let mut is_escaped = false;
+    let mut is_class = false;
+    for ch in pattern.chars() {
+        match ch {
+            _ if is_escaped => {
+                is_escaped = false;
+                translated.push(ch);
+            }
+            '\\' => {
+                is_escaped = true;
+                translated.push(ch);
+            }
+            '[' => {
+                is_class = true;
+                translated.push(ch);
+            }
+            ']' => {
+                is_class = false;
+                translated.push(ch);
+            }
+            '.' if !is_class => translated.push_str(r"[^\n\r]"),
+            '^' | '$' if !is_class => {
+                translated.push('\\');
+                translated.push(ch);
+            }
+            _ => translated.push(ch),
+        }
+    }
+    translated.push_str(if is_full { r")\z" } else { ")" });
+
+    utils::build_regex(&mut RegexBuilder::new(&translated))
+}
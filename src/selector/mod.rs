@@ -1,7 +1,8 @@
-pub use self::selector_impl::{JsonSelector, JsonSelectorMut};
+pub use self::selector_impl::{DescentOrder, JsonSelector, JsonSelectorMut, KeyMatch};
 
 mod cmp;
+mod functions;
 mod terms;
 mod selector_impl;
 mod value_walker;
-mod utils;
\ No newline at end of file
+pub(crate) mod utils;
\ No newline at end of file
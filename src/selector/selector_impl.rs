@@ -1,45 +1,76 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
-use serde_json::{Number, Value};
+use regex::Regex;
+use serde_json::Value;
 use serde_json::map::Entry;
 
 use JsonPathError;
-use paths::{ParserTokenHandler, PathParser, StrRange, tokens::*};
+use paths::{ParserMode, ParserTokenHandler, PathParser, StrRange, tokens::*};
+use super::functions;
 use super::utils;
+use super::value_walker::{Descent, Parents, ValueWalker};
 
 use super::terms::*;
 
+/// The order in which `..` visits the nodes below where it starts.
+///
+/// `PreOrder` visits them in document order, each node before its descendants,
+/// as RFC 9535 does. `BreadthFirst` visits them level by level, so the matches
+/// closest to where `..` starts come first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DescentOrder {
+    #[default]
+    PreOrder,
+    BreadthFirst,
+}
+
+/// How a member name in a path matches the member names of an object.
+///
+/// `Exact` compares them as they are, as RFC 9535 does. `AsciiCaseInsensitive`
+/// ignores the case of `A` to `Z`, and `CaseFold` compares the Unicode default case
+/// folding of both, so `Straße` matches `STRASSE`. `Nfc` compares the NFC forms,
+/// so a name in NFD matches its composed form. A name can match more than one
+/// member of an object, e.g. `price` matches both `Price` and `PRICE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyMatch {
+    #[default]
+    Exact,
+    AsciiCaseInsensitive,
+    CaseFold,
+    Nfc,
+}
+
 #[derive(Debug, Default)]
 pub struct JsonSelector<'a> {
     parser: Option<Rc<PathParser<'a>>>,
     value: Option<&'a Value>,
     tokens: Vec<ParseToken>,
     current: Option<Vec<&'a Value>>,
-    selectors: Vec<JsonSelector<'a>>,
+    // values computed from the current nodes: their member names after a `~`,
+    // or the result of an aggregate function like `.sum()`
+    computed: Option<Vec<Value>>,
+    filter_path: Option<FilterPath<'a>>,
+    union_path: Option<UnionPath<'a>>,
     selector_filter: FilterTerms<'a>,
+    mode: ParserMode,
+    descent_order: DescentOrder,
+    key_match: KeyMatch,
+    params: Rc<HashMap<String, Value>>,
+    // shared with the selectors of filters and unions, and only kept for a path with `^`
+    parents: Option<Rc<RefCell<Parents<'a>>>>,
 }
 
 impl<'a> JsonSelector<'a> {
     pub fn new(parser: PathParser<'a>) -> Self {
-        JsonSelector {
-            parser: Some(Rc::new(parser)),
-            value: None,
-            tokens: Vec::new(),
-            current: None,
-            selectors: Vec::new(),
-            selector_filter: FilterTerms(Vec::new()),
-        }
+        Self::new_ref(Rc::new(parser))
     }
 
     pub fn new_ref(parser: Rc<PathParser<'a>>) -> Self {
         JsonSelector {
             parser: Some(parser),
-            value: None,
-            tokens: Vec::new(),
-            current: None,
-            selectors: Vec::new(),
-            selector_filter: FilterTerms(Vec::new()),
+            ..Default::default()
         }
     }
 
@@ -55,6 +86,7 @@ impl<'a> JsonSelector<'a> {
 
     pub fn reset_value(&mut self) -> &mut Self {
         self.current = None;
+        self.computed = None;
         self
     }
 
@@ -63,12 +95,42 @@ impl<'a> JsonSelector<'a> {
         self
     }
 
+    pub fn descent_order(&mut self, order: DescentOrder) -> &mut Self {
+        self.descent_order = order;
+        self
+    }
+
+    pub fn key_match(&mut self, key_match: KeyMatch) -> &mut Self {
+        self.key_match = key_match;
+        self
+    }
+
+    /// Binds the value of the `$name` parameter, e.g. `$role` in `$.users[?(@.role == $role)]`.
+    /// A value is compared as it is, so it can not change the path.
+    pub fn bind(&mut self, name: &str, value: Value) -> &mut Self {
+        Rc::make_mut(&mut self.params).insert(name.to_string(), value);
+        self
+    }
+
+    /// Replaces all the bound parameters, e.g. with the members of a JSON object.
+    pub fn params<I, K>(&mut self, params: I) -> &mut Self
+        where
+            I: IntoIterator<Item=(K, Value)>,
+            K: Into<String>,
+    {
+        self.params = Rc::new(params.into_iter().map(|(name, value)| (name.into(), value)).collect());
+        self
+    }
+
     fn _select(&mut self) -> Result<(), JsonPathError> {
-        let parser = self.parser.take();
-        if let Some(parser) = parser.as_ref() {
-            let _ = parser.parse(self);
+        if let Some(parser) = self.parser.clone() {
+            if let Some(name) = parser.parameters().iter().find(|name| !self.params.contains_key(**name)) {
+                return Err(JsonPathError::Path(format!("parameter is not bound: ${}", name)));
+            }
+            self.mode = parser.mode();
+            self.parents = if parser.has_parent() { Some(Rc::default()) } else { None };
+            parser.parse(self).map_err(JsonPathError::Path)?;
         }
-        self.parser = parser;
 
         Ok(())
     }
@@ -76,6 +138,12 @@ impl<'a> JsonSelector<'a> {
     pub fn select_as<T: serde::de::DeserializeOwned>(&mut self) -> Result<Vec<T>, JsonPathError> {
         self._select()?;
 
+        if let Some(computed) = &self.computed {
+            return computed.iter()
+                .map(|v| T::deserialize(v).map_err(|e| JsonPathError::Serde(e.to_string())))
+                .collect();
+        }
+
         match &self.current {
             Some(vec) => {
                 let mut ret = Vec::new();
@@ -94,6 +162,10 @@ impl<'a> JsonSelector<'a> {
     pub fn select_as_str(&mut self) -> Result<String, JsonPathError> {
         self._select()?;
 
+        if let Some(computed) = &self.computed {
+            return serde_json::to_string(computed).map_err(|e| JsonPathError::Serde(e.to_string()));
+        }
+
         match &self.current {
             Some(r) => {
                 Ok(serde_json::to_string(r).map_err(|e| JsonPathError::Serde(e.to_string()))?)
@@ -102,62 +174,186 @@ impl<'a> JsonSelector<'a> {
         }
     }
 
+    /// Selects the matched nodes. The member names of a path ending in `~` and the
+    /// result of an aggregate function are not in the document, so `select` can not
+    /// return them.
     pub fn select(&mut self) -> Result<Vec<&'a Value>, JsonPathError> {
         self._select()?;
 
+        if self.computed.is_some() {
+            return Err(JsonPathError::Path("member names (`~`) and aggregate functions are selected with select_values, select_as or select_as_str".to_string()));
+        }
+
         match &self.current {
             Some(r) => Ok(r.to_vec()),
             _ => Err(JsonPathError::EmptyValue),
         }
     }
 
-    fn compute_absolute_path_filter<F>(&mut self, token: &ParseToken, parse_value_reader: &F) -> bool
+    /// Selects copies of the matched nodes, or their member names (a string or an
+    /// array index) for a path ending in `~`, e.g. `$.store.*~`, or the result of an
+    /// aggregate function, e.g. `$..price.sum()`, which is empty when it has none.
+    pub fn select_values(&mut self) -> Result<Vec<Value>, JsonPathError> {
+        self._select()?;
+
+        if let Some(computed) = &self.computed {
+            return Ok(computed.to_vec());
+        }
+
+        match &self.current {
+            Some(r) => Ok(r.iter().map(|v| (*v).clone()).collect()),
+            _ => Err(JsonPathError::EmptyValue),
+        }
+    }
+
+    /// Selects the normalized paths (RFC 9535 section 2.7) of the matched nodes,
+    /// e.g. `$['store']['book'][0]`, in the same order as `select`.
+    pub fn select_paths(&mut self) -> Result<Vec<String>, JsonPathError> {
+        self._select()?;
+
+        match (&self.current, self.value) {
+            (Some(r), Some(value)) => Ok(utils::to_normalized_paths(value, r)),
+            _ => Err(JsonPathError::EmptyValue),
+        }
+    }
+
+    fn compute_filter_path<F>(&mut self, token: &ParseToken, parse_value_reader: &F) -> bool
         where
             F: Fn(&StrRange) -> &'a str
     {
-        if !self.selectors.is_empty() {
-            match token {
-                ParseToken::Absolute | ParseToken::Relative | ParseToken::Filter(_) => {
-                    let selector = self.selectors.pop().unwrap();
-
-                    if let Some(current) = &selector.current {
-                        let term = current.into();
-
-                        if let Some(s) = self.selectors.last_mut() {
-                            s.selector_filter.push_term(Some(term));
-                        } else {
-                            self.selector_filter.push_term(Some(term));
-                        }
-                    } else {
-                        unreachable!()
-                    }
-                }
-                _ => {}
+        let is_path_token = match self.filter_path.as_mut() {
+            Some(filter_path) => filter_path.accept(token),
+            None => return false,
+        };
+
+        if !is_path_token {
+            let filter_path = self.filter_path.take().unwrap();
+            let term = if filter_path.has_computed() {
+                self.compute_filter_candidates();
+                filter_path.into_computed(self.selector_filter.candidates().len())
+            } else {
+                filter_path.into_term(self.mode)
+            };
+            self.selector_filter.push_term(term);
+            return false;
+        }
+
+        if let Some(filter_path) = self.filter_path.as_mut() {
+            for selector in filter_path.selectors.iter_mut() {
+                selector.handle(token, parse_value_reader);
             }
         }
+        true
+    }
+
+    fn compute_union_path<F>(&mut self, token: &ParseToken, parse_value_reader: &F) -> bool
+        where
+            F: Fn(&StrRange) -> &'a str
+    {
+        let is_union_token = match self.union_path.as_mut() {
+            Some(union_path) => union_path.accept(token),
+            None => return false,
+        };
 
-        if self.selectors.is_empty() {
+        if !is_union_token {
+            let union_path = self.union_path.take().unwrap();
+            self.current = Some(union_path.into_nodes());
             return false;
         }
 
-        self.selectors.last_mut().unwrap().handle(token, parse_value_reader);
+        let is_selector_start = *token == ParseToken::Array
+            && self.union_path.as_ref().is_some_and(|union_path| union_path.depth == 1);
+
+        if is_selector_start {
+            let selectors = self.union_path.as_ref().unwrap().targets.iter()
+                .map(|v| self.new_filter_selector(v))
+                .collect();
+            self.union_path.as_mut().unwrap().selectors = selectors;
+        }
+
+        if let Some(union_path) = self.union_path.as_mut() {
+            for selector in union_path.selectors.iter_mut() {
+                selector.handle(token, parse_value_reader);
+            }
+
+            if *token == ParseToken::ArrayEof && union_path.depth == 0 {
+                union_path.end_selector();
+            }
+        }
         true
     }
+
+    fn new_filter_selector(&self, current: &'a Value) -> JsonSelector<'a> {
+        JsonSelector {
+            parser: self.parser.clone(),
+            value: self.value,
+            current: Some(vec![current]),
+            mode: self.mode,
+            descent_order: self.descent_order,
+            key_match: self.key_match,
+            params: self.params.clone(),
+            parents: self.parents.clone(),
+            ..Default::default()
+        }
+    }
+
+    // remembers the parents of the nodes a step from `nodes` can reach, for `^`
+    fn track_parents(&self, nodes: Option<&Vec<&'a Value>>, is_descendant: bool) {
+        if let (Some(parents), Some(nodes)) = (&self.parents, nodes) {
+            ValueWalker::track_parents(nodes, is_descendant, &mut parents.borrow_mut());
+        }
+    }
+
+    fn descent(&self) -> Descent {
+        Descent {
+            depth: self.parser.as_ref().and_then(|parser| parser.max_descent_depth()),
+            order: self.descent_order,
+        }
+    }
+
+    fn compute_filter_candidates(&mut self) {
+        if self.selector_filter.has_candidates() {
+            return;
+        }
+
+        let is_descendant = self.is_last_before_token_match(ParseToken::Leaves);
+        let descent = self.descent();
+        // with context variables the members of an object are filtered, so they have names
+        let members = self.parser.as_ref().is_some_and(|parser| parser.has_context());
+        self.track_parents(self.current.as_ref(), is_descendant);
+        let candidates = match &self.current {
+            Some(current) => match self.mode {
+                ParserMode::Rfc9535 if is_descendant => ValueWalker::all(current, descent),
+                ParserMode::Rfc9535 => ValueWalker::next_all(current),
+                ParserMode::Default if is_descendant && members => ValueWalker::next_members_dedup(&ValueWalker::all(current, descent)),
+                ParserMode::Default if is_descendant => ValueWalker::next_dedup(&ValueWalker::all(current, descent)),
+                ParserMode::Default if members => ValueWalker::next_members_dedup(current),
+                ParserMode::Default => ValueWalker::next_dedup(current),
+            },
+            _ => Vec::new(),
+        };
+        self.selector_filter.set_candidates(candidates);
+    }
+
+    fn take_selector_targets(&mut self) -> Option<Vec<&'a Value>> {
+        let current = self.current.take();
+        let targets = if self.is_last_before_token_match(ParseToken::Leaves) {
+            let descent = self.descent();
+            current.map(|current| ValueWalker::self_and_all(&current, descent))
+        } else {
+            current
+        };
+        self.track_parents(targets.as_ref(), false);
+        targets
+    }
 }
 
 impl<'a> JsonSelector<'a> {
     fn visit_absolute(&mut self) {
         if self.current.is_some() {
             if let Some(value) = self.value {
-                let selector = JsonSelector {
-                    parser: None,
-                    value: Some(value),
-                    tokens: Vec::new(),
-                    current: Some(vec![value]),
-                    selectors: Vec::new(),
-                    selector_filter: FilterTerms(Vec::new()),
-                };
-                self.selectors.push(selector);
+                let selector = self.new_filter_selector(value);
+                self.filter_path = Some(FilterPath::new(false, vec![selector]));
             }
             return;
         }
@@ -167,83 +363,107 @@ impl<'a> JsonSelector<'a> {
         }
     }
 
-    fn visit_relative(&mut self) {
-        if let Some(ParseToken::Array) = self.tokens.last() {
-            let array_token = self.tokens.pop();
-            if let Some(ParseToken::Leaves) = self.tokens.last() {
-                self.tokens.pop();
-                self.current = self.selector_filter.collect_all(self.current.take());
-            }
-            self.tokens.push(array_token.unwrap());
+    fn visit_selectors(&mut self) {
+        if !self.selector_filter.is_term_empty() {
+            unimplemented!("union syntax in filter");
         }
-        self.selector_filter.new_filter_context();
-    }
 
-    fn visit_array_eof(&mut self) {
-        if self.is_last_before_token_match(ParseToken::Array) {
-            if let Some(Some(e)) = self.selector_filter.pop_term() {
-                if let ExprTerm::String(key) = e {
-                    self.current = self.selector_filter.filter_next_with_str(self.current.take(), key);
-                    self.tokens.pop();
-                    return;
-                }
+        let targets = self.take_selector_targets().unwrap_or_default();
+        self.union_path = Some(UnionPath::new(targets));
+    }
 
-                self.selector_filter.push_term(Some(e));
-            }
+    fn visit_parent(&mut self) {
+        if let (Some(current), Some(parents)) = (&self.current, &self.parents) {
+            let parents = parents.borrow();
+            let mut visited = HashSet::new();
+            let nodes = current.iter()
+                .filter_map(|v| parents.get(&(*v as *const Value)).copied())
+                .filter(|v| visited.insert(*v as *const Value))
+                .collect();
+            self.current = Some(nodes);
         }
+    }
 
-        if self.is_last_before_token_match(ParseToken::Leaves) {
-            self.tokens.pop();
-            self.tokens.pop();
-            if let Some(Some(e)) = self.selector_filter.pop_term() {
-                let selector_filter_consumed = match e {
-                    ExprTerm::Number(n) => {
-                        self.current = self.selector_filter.collect_all_with_num(self.current.take(), utils::to_f64(&n));
-                        self.selector_filter.pop_term();
-                        true
-                    }
-                    ExprTerm::String(key) => {
-                        self.current = self.selector_filter.collect_all_with_str(self.current.take(), key);
-                        self.selector_filter.pop_term();
-                        true
-                    }
-                    _ => {
-                        self.selector_filter.push_term(Some(e));
-                        false
-                    }
-                };
+    fn visit_property_name(&mut self) {
+        if let (Some(current), Some(value)) = (&self.current, self.value) {
+            self.computed = Some(utils::to_member_names(value, current));
+        }
+    }
 
-                if selector_filter_consumed {
-                    return;
-                }
-            }
+    fn visit_aggregate(&mut self, token: &AggregateToken) {
+        if let Some(current) = &self.current {
+            self.computed = Some(functions::aggregate(token, current).into_iter().collect());
         }
+    }
+
+    fn visit_context(&mut self, token: &ContextToken) {
+        self.compute_filter_candidates();
+        let candidates = self.selector_filter.candidates();
+        let values = match self.value {
+            Some(value) => match token {
+                ContextToken::Property => utils::to_members(value, candidates).into_iter()
+                    .map(|member| member.map(|(_, name)| name))
+                    .collect(),
+                ContextToken::ParentProperty => utils::to_parent_names(value, candidates),
+                ContextToken::Path => utils::to_normalized_paths(value, candidates).into_iter()
+                    .map(|path| Some(Value::String(path)))
+                    .collect(),
+            },
+            None => vec![None; candidates.len()],
+        };
+        self.selector_filter.push_term(ExprTerm::Values(values));
+    }
+
+    fn visit_relative(&mut self) {
+        self.compute_filter_candidates();
+        let selectors = self.selector_filter.candidates().iter()
+            .map(|v| self.new_filter_selector(v))
+            .collect();
+        self.filter_path = Some(FilterPath::new(true, selectors));
+    }
 
-        if let Some(Some(e)) = self.selector_filter.pop_term() {
+    fn visit_array_eof(&mut self) {
+        if let Some(e) = self.selector_filter.pop_term() {
             match e {
                 ExprTerm::Number(n) => {
-                    self.current = self.selector_filter.collect_next_with_num(self.current.take(), utils::to_f64(&n));
+                    let targets = self.take_selector_targets();
+                    self.current = match self.mode {
+                        ParserMode::Default => self.selector_filter.collect_next_with_num(targets, utils::to_f64(&n)),
+                        ParserMode::Rfc9535 => self.selector_filter.collect_next_with_index(targets, utils::to_f64(&n) as isize),
+                    };
                 }
                 ExprTerm::String(key) => {
-                    self.current = self.selector_filter.collect_next_with_str(self.current.take(), &[key]);
+                    let targets = self.take_selector_targets();
+                    self.current = self.selector_filter.collect_next_with_str(targets, &[key], self.key_match);
                 }
-                ExprTerm::Json(rel, _, v) => {
-                    if v.is_empty() {
-                        self.current = Some(Vec::new());
-                    } else if let Some(vec) = rel {
-                        self.current = Some(vec);
-                    } else {
-                        self.current = Some(v);
-                    }
-                }
-                ExprTerm::Bool(false) => {
-                    self.current = Some(vec![]);
-                }
-                _ => {}
+                _ => self.visit_filter_result(e),
             }
         }
 
-        self.tokens.pop();
+        if let Some(ParseToken::Array) = self.tokens.pop() {
+            if let Some(ParseToken::Leaves) = self.tokens.last() {
+                self.tokens.pop();
+            }
+        }
+    }
+
+    fn visit_filter_result(&mut self, e: ExprTerm<'a>) {
+        self.compute_filter_candidates();
+        let candidates = self.selector_filter.take_candidates();
+
+        match e.into_logical() {
+            ExprTerm::Matched(indices) => {
+                self.current = Some(indices.into_iter().map(|i| candidates[i]).collect());
+            }
+            ExprTerm::Bool(false) => {
+                self.current = Some(Vec::new());
+            }
+            _ => {
+                if self.mode == ParserMode::Rfc9535 {
+                    self.current = Some(candidates);
+                }
+            }
+        }
     }
 
     fn is_last_before_token_match(&mut self, token: ParseToken) -> bool {
@@ -255,20 +475,23 @@ impl<'a> JsonSelector<'a> {
     }
 
     fn visit_all(&mut self) {
-        if let Some(ParseToken::Array) = self.tokens.last() {
-            self.tokens.pop();
-        }
-
         match self.tokens.last() {
+            Some(ParseToken::Array) => {
+                let targets = self.take_selector_targets();
+                self.current = self.selector_filter.collect_next_all(targets);
+            }
             Some(ParseToken::Leaves) => {
                 self.tokens.pop();
-                self.current = self.selector_filter.collect_all(self.current.take());
+                self.track_parents(self.current.as_ref(), true);
+                self.current = self.selector_filter.collect_all(self.current.take(), self.descent());
             }
             Some(ParseToken::In) => {
                 self.tokens.pop();
+                self.track_parents(self.current.as_ref(), false);
                 self.current = self.selector_filter.collect_next_all(self.current.take());
             }
             _ => {
+                self.track_parents(self.current.as_ref(), false);
                 self.current = self.selector_filter.collect_next_all(self.current.take());
             }
         }
@@ -276,31 +499,22 @@ impl<'a> JsonSelector<'a> {
 
     fn visit_key(&mut self, key: &'a str) {
         if let Some(ParseToken::Array) = self.tokens.last() {
-            self.selector_filter.push_term(Some(ExprTerm::String(key)));
+            self.selector_filter.push_term(ExprTerm::String(key));
             return;
         }
 
         if let Some(t) = self.tokens.pop() {
-            if self.selector_filter.is_term_empty() {
-                match t {
-                    ParseToken::Leaves => {
-                        self.current = self.selector_filter.collect_all_with_str(self.current.take(), key)
-                    }
-                    ParseToken::In => {
-                        self.current = self.selector_filter.collect_next_with_str(self.current.take(), &[key])
-                    }
-                    _ => {}
+            match t {
+                ParseToken::Leaves => {
+                    let descent = self.descent();
+                    self.track_parents(self.current.as_ref(), true);
+                    self.current = self.selector_filter.collect_all_with_str(self.current.take(), key, descent, self.key_match)
                 }
-            } else {
-                match t {
-                    ParseToken::Leaves => {
-                        self.current = self.selector_filter.filter_all_with_str(self.current.take(), key);
-                    }
-                    ParseToken::In => {
-                        self.current = self.selector_filter.filter_next_with_str(self.current.take(), key);
-                    }
-                    _ => {}
+                ParseToken::In => {
+                    self.track_parents(self.current.as_ref(), false);
+                    self.current = self.selector_filter.collect_next_with_str(self.current.take(), &[key], self.key_match)
                 }
+                _ => {}
             }
         }
     }
@@ -310,88 +524,121 @@ impl<'a> JsonSelector<'a> {
             unimplemented!("keys in filter");
         }
 
-        if let Some(ParseToken::Array) = self.tokens.pop() {
-            self.current = self.selector_filter.collect_next_with_str(self.current.take(), keys);
+        if let Some(ParseToken::Array) = self.tokens.last() {
+            let targets = self.take_selector_targets();
+            self.current = self.selector_filter.collect_next_with_str(targets, keys, self.key_match);
         } else {
             unreachable!();
         }
     }
 
     fn visit_filter(&mut self, ft: &FilterToken) {
+        if let FilterToken::Not = ft {
+            return self.visit_not();
+        }
+
         let right = match self.selector_filter.pop_term() {
-            Some(Some(right)) => right,
-            Some(None) => ExprTerm::Json(
-                None,
-                None,
-                match &self.current {
-                    Some(current) => current.to_vec(),
-                    _ => unreachable!(),
-                },
-            ),
+            Some(right) => right,
             _ => panic!("empty term right"),
         };
 
-        let mut left = match self.selector_filter.pop_term() {
-            Some(Some(left)) => left,
-            Some(None) => ExprTerm::Json(
-                None,
-                None,
-                match &self.current {
-                    Some(current) => current.to_vec(),
-                    _ => unreachable!(),
-                },
-            ),
+        let left = match self.selector_filter.pop_term() {
+            Some(left) => left,
             _ => panic!("empty term left"),
         };
 
+        let mode = self.mode;
         let expr = match ft {
-            FilterToken::Equal => left.eq_(right),
-            FilterToken::NotEqual => left.ne_(right),
-            FilterToken::Greater => left.gt(right),
-            FilterToken::GreaterOrEqual => left.ge(right),
-            FilterToken::Little => left.lt(right),
-            FilterToken::LittleOrEqual => left.le(right),
+            FilterToken::Equal => left.eq_(right, mode),
+            FilterToken::NotEqual => left.ne_(right, mode),
+            FilterToken::Greater => left.gt(right, mode),
+            FilterToken::GreaterOrEqual => left.ge(right, mode),
+            FilterToken::Little => left.lt(right, mode),
+            FilterToken::LittleOrEqual => left.le(right, mode),
             FilterToken::And => left.and(right),
             FilterToken::Or => left.or(right),
+            FilterToken::Matches => left.matches(right),
+            FilterToken::In => left.in_(right),
+            FilterToken::Nin => left.nin(right),
+            FilterToken::SubsetOf => left.subset_of(right),
+            FilterToken::AnyOf => left.any_of(right),
+            FilterToken::NoneOf => left.none_of(right),
+            FilterToken::Not => unreachable!(),
+        };
+
+        self.selector_filter.push_term(expr);
+    }
+
+    fn visit_not(&mut self) {
+        let term = match self.selector_filter.pop_term() {
+            Some(term) => term,
+            _ => panic!("empty term"),
+        };
+
+        self.compute_filter_candidates();
+        let len = self.selector_filter.candidates().len();
+        self.selector_filter.push_term(term.not(len));
+    }
+
+    fn visit_arithmetic(&mut self, token: &ArithmeticToken) {
+        let right = match self.selector_filter.pop_term() {
+            Some(right) => right,
+            _ => panic!("empty term right"),
+        };
+
+        let left = match self.selector_filter.pop_term() {
+            Some(left) => left,
+            _ => panic!("empty term left"),
+        };
+
+        self.compute_filter_candidates();
+        let len = self.selector_filter.candidates().len();
+        self.selector_filter.push_term(left.arithmetic(token, right, len));
+    }
+
+    fn visit_function(&mut self, function: &FunctionToken) {
+        let mut args = Vec::new();
+        for _ in function.parameters() {
+            match self.selector_filter.pop_term() {
+                Some(arg) => args.push(arg),
+                _ => panic!("empty function argument"),
+            }
+        }
+        args.reverse();
+
+        self.compute_filter_candidates();
+        let len = self.selector_filter.candidates().len();
+        let term = match function {
+            FunctionToken::Custom { name, .. } => match self.parser.as_ref().and_then(|parser| parser.function(name)) {
+                Some(custom) => ExprTerm::call_custom(custom, args, len),
+                None => unreachable!(),
+            },
+            _ => ExprTerm::call(function, args, len),
         };
+        self.selector_filter.push_term(term);
+    }
 
-        self.selector_filter.push_term(Some(expr));
+    fn visit_key_pattern(&mut self, regex: &Regex) {
+        if !self.selector_filter.is_term_empty() {
+            unimplemented!("key pattern in filter");
+        }
+
+        if let Some(ParseToken::Array) = self.tokens.last() {
+            let targets = self.take_selector_targets();
+            self.current = self.selector_filter.collect_next_with_key_pattern(targets, regex);
+        } else {
+            unreachable!();
+        }
     }
 
-    fn visit_range(&mut self, from: &Option<isize>, to: &Option<isize>, step: &Option<usize>) {
+    fn visit_range(&mut self, from: &Option<isize>, to: &Option<isize>, step: &Option<isize>) {
         if !self.selector_filter.is_term_empty() {
             unimplemented!("range syntax in filter");
         }
 
-        if let Some(ParseToken::Array) = self.tokens.pop() {
-            let mut tmp = Vec::new();
-            if let Some(current) = &self.current {
-                for v in current {
-                    if let Value::Array(vec) = v {
-                        let from = if let Some(from) = from {
-                            utils::abs_index(*from, vec.len())
-                        } else {
-                            0
-                        };
-
-                        let to = if let Some(to) = to {
-                            utils::abs_index(*to, vec.len())
-                        } else {
-                            vec.len()
-                        };
-
-                        for i in (from..to).step_by(match step {
-                            Some(step) => *step,
-                            _ => 1,
-                        }) {
-                            if let Some(v) = vec.get(i) {
-                                tmp.push(v);
-                            }
-                        }
-                    }
-                }
-            }
-            self.current = Some(tmp);
+        if let Some(ParseToken::Array) = self.tokens.last() {
+            let targets = self.take_selector_targets();
+            self.current = self.selector_filter.collect_next_with_range(targets, *from, *to, *step);
         } else {
             unreachable!();
         }
@@ -402,13 +649,17 @@ impl<'a> JsonSelector<'a> {
             unimplemented!("union syntax in filter");
         }
 
-        if let Some(ParseToken::Array) = self.tokens.pop() {
+        if let Some(ParseToken::Array) = self.tokens.last() {
             let mut tmp = Vec::new();
-            if let Some(current) = &self.current {
+            if let Some(current) = &self.take_selector_targets() {
                 for v in current {
                     if let Value::Array(vec) = v {
                         for i in indices {
-                            if let Some(v) = vec.get(utils::abs_index(*i, vec.len())) {
+                            let index = match self.mode {
+                                ParserMode::Default => Some(utils::abs_index(*i, vec.len())),
+                                ParserMode::Rfc9535 => utils::normalize_index(*i, vec.len()),
+                            };
+                            if let Some(v) = index.and_then(|i| vec.get(i)) {
                                 tmp.push(v);
                             }
                         }
@@ -430,7 +681,11 @@ impl<'a> ParserTokenHandler<'a> for JsonSelector<'a> {
     {
         debug!("token: {:?}, stack: {:?}", token, self.tokens);
 
-        if self.compute_absolute_path_filter(token, parse_value_reader) {
+        if self.compute_filter_path(token, parse_value_reader) {
+            return;
+        }
+
+        if self.compute_union_path(token, parse_value_reader) {
             return;
         }
 
@@ -442,8 +697,14 @@ impl<'a> ParserTokenHandler<'a> for JsonSelector<'a> {
             }
             ParseToken::ArrayEof => self.visit_array_eof(),
             ParseToken::All => self.visit_all(),
+            ParseToken::PropertyName => self.visit_property_name(),
+            ParseToken::Parent => self.visit_parent(),
+            ParseToken::Aggregate(token) => self.visit_aggregate(token),
             ParseToken::Bool(b) => {
-                self.selector_filter.push_term(Some(ExprTerm::Bool(*b)));
+                self.selector_filter.push_term(ExprTerm::Bool(*b));
+            }
+            ParseToken::Null => {
+                self.selector_filter.push_term(ExprTerm::Null);
             }
             ParseToken::Key(s) => {
                 let key = parse_value_reader(s);
@@ -453,12 +714,41 @@ impl<'a> ParserTokenHandler<'a> for JsonSelector<'a> {
                 let keys: Vec<&str> = keys.iter().map(|s| { parse_value_reader(s) }).collect();
                 self.visit_keys(&keys)
             }
+            ParseToken::KeyPattern(s) => {
+                let literal = parse_value_reader(s);
+                match self.parser.as_ref().and_then(|parser| parser.regex(literal)) {
+                    Some(regex) => self.visit_key_pattern(&regex),
+                    None => unreachable!(),
+                }
+            }
             ParseToken::Number(v) => {
-                self.selector_filter.push_term(Some(ExprTerm::Number(Number::from_f64(*v).unwrap())));
+                self.selector_filter.push_term(ExprTerm::Number(v.clone()));
+            }
+            ParseToken::Regex(s) => {
+                let literal = parse_value_reader(s);
+                match self.parser.as_ref().and_then(|parser| parser.regex(literal)) {
+                    Some(regex) => self.selector_filter.push_term(ExprTerm::Regex(RegexTerm(regex))),
+                    None => unreachable!(),
+                }
             }
+            ParseToken::Literal(v) => {
+                self.selector_filter.push_term(ExprTerm::Literal(v.clone()));
+            }
+            ParseToken::Parameter(s) => {
+                let name = parse_value_reader(s);
+                match self.params.get(name) {
+                    Some(value) => self.selector_filter.push_term(ExprTerm::from_value(value)),
+                    None => unreachable!(),
+                }
+            }
+            ParseToken::Function(f) => self.visit_function(f),
+            ParseToken::Arithmetic(token) => self.visit_arithmetic(token),
+            ParseToken::Context(token) => self.visit_context(token),
             ParseToken::Filter(ref ft) => self.visit_filter(ft),
             ParseToken::Range(from, to, step) => self.visit_range(from, to, step),
             ParseToken::Union(indices) => self.visit_union(indices),
+            ParseToken::Selectors => self.visit_selectors(),
+            ParseToken::Argument => unreachable!(),
             ParseToken::Eof => {
                 debug!("visit_token eof");
             }
@@ -466,10 +756,138 @@ impl<'a> ParserTokenHandler<'a> for JsonSelector<'a> {
     }
 }
 
+//
+// A path inside a filter. A `@` path is run once per filter candidate and a `$` path
+// once from the root; it ends at the first token that does not continue it.
+//
+#[derive(Debug)]
+struct FilterPath<'a> {
+    is_relative: bool,
+    depth: usize,
+    is_pending: bool,
+    selectors: Vec<JsonSelector<'a>>,
+}
+
+impl<'a> FilterPath<'a> {
+    fn new(is_relative: bool, selectors: Vec<JsonSelector<'a>>) -> Self {
+        FilterPath {
+            is_relative,
+            depth: 0,
+            is_pending: false,
+            selectors,
+        }
+    }
+
+    fn accept(&mut self, token: &ParseToken) -> bool {
+        match token {
+            ParseToken::Array => {
+                self.depth += 1;
+                self.is_pending = false;
+                true
+            }
+            ParseToken::ArrayEof if self.depth > 0 => {
+                self.depth -= 1;
+                true
+            }
+            _ if self.depth > 0 => true,
+            ParseToken::In | ParseToken::Leaves => {
+                self.is_pending = true;
+                true
+            }
+            ParseToken::PropertyName | ParseToken::Parent | ParseToken::Aggregate(_) => true,
+            _ if self.is_pending => {
+                self.is_pending = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn has_computed(&self) -> bool {
+        self.selectors.iter().any(|selector| selector.computed.is_some())
+    }
+
+    // a `$` path has the same values for each of the `len` filter candidates
+    fn into_computed(self, len: usize) -> ExprTerm<'a> {
+        let mut computed = self.selectors.into_iter().map(|s| s.computed.unwrap_or_default());
+
+        if self.is_relative {
+            return ExprTerm::Computed(computed.collect());
+        }
+
+        let computed = computed.next().unwrap_or_default();
+        ExprTerm::Computed(vec![computed; len])
+    }
+
+    fn into_term(self, mode: ParserMode) -> ExprTerm<'a> {
+        let mut nodes = self.selectors.into_iter().map(|s| s.current.unwrap_or_default());
+
+        if self.is_relative {
+            return ExprTerm::Relative(nodes.collect());
+        }
+
+        let nodes = nodes.next().unwrap_or_default();
+        match mode {
+            ParserMode::Default => (&nodes).into(),
+            ParserMode::Rfc9535 => ExprTerm::Json(nodes),
+        }
+    }
+}
+
+//
+// A bracket with a mixed union of selectors. Each selector is run once per target node,
+// and the results are concatenated per target node in the order of the selectors.
+//
+#[derive(Debug)]
+struct UnionPath<'a> {
+    depth: usize,
+    targets: Vec<&'a Value>,
+    selectors: Vec<JsonSelector<'a>>,
+    nodes: Vec<Vec<&'a Value>>,
+}
+
+impl<'a> UnionPath<'a> {
+    fn new(targets: Vec<&'a Value>) -> Self {
+        UnionPath {
+            depth: 0,
+            nodes: vec![Vec::new(); targets.len()],
+            targets,
+            selectors: Vec::new(),
+        }
+    }
+
+    fn accept(&mut self, token: &ParseToken) -> bool {
+        match token {
+            ParseToken::Array => {
+                self.depth += 1;
+                true
+            }
+            ParseToken::ArrayEof if self.depth > 0 => {
+                self.depth -= 1;
+                true
+            }
+            ParseToken::ArrayEof => false,
+            _ => true,
+        }
+    }
+
+    fn end_selector(&mut self) {
+        for (nodes, selector) in self.nodes.iter_mut().zip(self.selectors.drain(..)) {
+            nodes.extend(selector.current.unwrap_or_default());
+        }
+    }
+
+    fn into_nodes(self) -> Vec<&'a Value> {
+        self.nodes.into_iter().flatten().collect()
+    }
+}
+
 #[derive(Default)]
 pub struct JsonSelectorMut<'a> {
     value: Option<Value>,
     parser: Option<Rc<PathParser<'a>>>,
+    key_match: KeyMatch,
+    params: Rc<HashMap<String, Value>>,
 }
 
 impl<'a> JsonSelectorMut<'a> {
@@ -481,6 +899,8 @@ impl<'a> JsonSelectorMut<'a> {
         JsonSelectorMut {
             value: None,
             parser: Some(parser),
+            key_match: KeyMatch::default(),
+            params: Rc::default(),
         }
     }
 
@@ -499,6 +919,28 @@ impl<'a> JsonSelectorMut<'a> {
         self
     }
 
+    /// Sets how member names match, as `JsonSelector::key_match` does.
+    pub fn key_match(&mut self, key_match: KeyMatch) -> &mut Self {
+        self.key_match = key_match;
+        self
+    }
+
+    /// Binds the value of the `$name` parameter, as `JsonSelector::bind` does.
+    pub fn bind(&mut self, name: &str, value: Value) -> &mut Self {
+        Rc::make_mut(&mut self.params).insert(name.to_string(), value);
+        self
+    }
+
+    /// Replaces all the bound parameters, as `JsonSelector::params` does.
+    pub fn params<I, K>(&mut self, params: I) -> &mut Self
+        where
+            I: IntoIterator<Item=(K, Value)>,
+            K: Into<String>,
+    {
+        self.params = Rc::new(params.into_iter().map(|(name, value)| (name.into(), value)).collect());
+        self
+    }
+
     pub fn take(&mut self) -> Option<Value> {
         self.value.take()
     }
@@ -526,6 +968,8 @@ impl<'a> JsonSelectorMut<'a> {
             return Err(JsonPathError::EmptyValue);
         }
 
+        selector.key_match(self.key_match);
+        selector.params = Rc::clone(&self.params);
         selector.select()
     }
 
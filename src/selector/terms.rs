@@ -1,290 +1,505 @@
 use std::collections::HashSet;
+use std::rc::Rc;
 
+use regex::Regex;
 use serde_json::{Number, Value};
 
+use paths::ParserMode;
+use paths::FunctionArgument;
+use paths::function_registry::{CustomFunction, FunctionBody};
+use paths::tokens::{ArithmeticToken, FunctionToken, FunctionType};
+
 use super::cmp::*;
+use super::functions::{self, RegexMatcher};
+use super::selector_impl::KeyMatch;
 use super::utils;
-use super::value_walker::ValueWalker;
+use super::value_walker::{Descent, ValueWalker};
 
 #[derive(Debug, PartialEq)]
 pub enum ExprTerm<'a> {
     String(&'a str),
     Number(Number),
     Bool(bool),
-    Json(Option<Vec<&'a Value>>, Option<FilterKey<'a>>, Vec<&'a Value>),
+    Null,
+    // nodes of a `$` path
+    Json(Vec<&'a Value>),
+    // nodes of a `@` path, one list per filter candidate
+    Relative(Vec<Vec<&'a Value>>),
+    // indices of the filter candidates an expression holds for
+    Matched(Vec<usize>),
+    // a `/pattern/flags` literal
+    Regex(RegexTerm),
+    // an inline JSON array or object
+    Literal(Value),
+    // results of a ValueType function or a context variable like `@property`, one per filter candidate
+    Values(Vec<Option<Value>>),
+    // member names of a path ending in `~`, or the result of an aggregate function,
+    // one list per filter candidate
+    Computed(Vec<Vec<Value>>),
+}
+
+// a regex compiled by the `PathParser`, equal only to itself
+#[derive(Debug)]
+pub struct RegexTerm(pub Rc<Regex>);
+
+impl PartialEq for RegexTerm {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 impl<'a> ExprTerm<'a> {
-    fn cmp_string<C>(s1: &str, other: &mut ExprTerm<'a>, cmp_fn: &C) -> ExprTerm<'a>
-        where
-            C: Cmp,
-    {
-        match other {
-            ExprTerm::String(s2) => {
-                let p1 = utils::to_path_str(s1);
-                let p2 = utils::to_path_str(s2);
-                ExprTerm::Bool(cmp_fn.cmp_string(p1.get_key(), p2.get_key()))
-            }
-            ExprTerm::Json(_, _, _) => unreachable!(),
-            _ => ExprTerm::Bool(cmp_fn.default()),
+    /// The term of a value given from outside the path, like a bound parameter.
+    pub fn from_value(value: &Value) -> ExprTerm<'a> {
+        match value {
+            Value::Number(n) => ExprTerm::Number(n.clone()),
+            Value::Bool(b) => ExprTerm::Bool(*b),
+            Value::Null => ExprTerm::Null,
+            _ => ExprTerm::Literal(value.clone()),
         }
     }
 
-    fn cmp_number<C>(n1: &Number, other: &mut ExprTerm<'a>, cmp_fn: &C) -> ExprTerm<'a>
-        where
-            C: Cmp,
-    {
-        match other {
-            ExprTerm::Number(n2) => ExprTerm::Bool(cmp_fn.cmp_f64(utils::to_f64(n1), utils::to_f64(n2))),
-            ExprTerm::Json(_, _, _) => unreachable!(),
-            _ => ExprTerm::Bool(cmp_fn.default()),
+    fn to_value(&self) -> Option<Value> {
+        match self {
+            ExprTerm::String(s) => Some(Value::String(utils::to_path_str(s).get_key().to_string())),
+            ExprTerm::Number(n) => Some(Value::Number(n.clone())),
+            ExprTerm::Bool(b) => Some(Value::Bool(*b)),
+            ExprTerm::Null => Some(Value::Null),
+            ExprTerm::Literal(v) => Some(v.clone()),
+            _ => None,
         }
     }
 
-    fn cmp_bool<C>(b1: &bool, other: &mut ExprTerm<'a>, cmp_fn: &C) -> ExprTerm<'a>
-        where
-            C: Cmp,
-    {
-        match other {
-            ExprTerm::Bool(b2) => ExprTerm::Bool(cmp_fn.cmp_bool(*b1, *b2)),
-            ExprTerm::Json(_, _, _) => unreachable!(),
-            _ => ExprTerm::Bool(cmp_fn.default()),
+    fn nodes<'b>(&'b self, literal: &'b Option<Value>, index: usize) -> Vec<&'b Value> {
+        match self {
+            ExprTerm::Relative(vec) => vec[index].to_vec(),
+            ExprTerm::Json(vec) => vec.to_vec(),
+            ExprTerm::Values(vec) => vec[index].iter().collect(),
+            ExprTerm::Computed(vec) => vec[index].iter().collect(),
+            _ => literal.iter().collect(),
         }
     }
 
-    fn cmp_json_string<C>(s2: &str,
-                          fk1: &Option<FilterKey>,
-                          vec1: &[&'a Value],
-                          cmp_fn: &C) -> Vec<&'a Value>
-        where
-            C: Cmp
-    {
-        let path_str = utils::to_path_str(s2);
-        vec1.iter().filter(|v1| match v1 {
-            Value::String(s1) => {
-                cmp_fn.cmp_string(s1, path_str.get_key())
-            }
-            Value::Object(map1) => {
-                if let Some(FilterKey::String(k)) = fk1 {
-                    if let Some(Value::String(s1)) = map1.get(*k) {
-                        return cmp_fn.cmp_string(s1, path_str.get_key());
-                    }
-                }
-                cmp_fn.default()
-            }
-            _ => cmp_fn.default(),
-        }).copied().collect()
+    fn operand<'b>(&'b self, literal: &'b Option<Value>, index: usize) -> Option<&'b Value> {
+        match self {
+            ExprTerm::Relative(vec) => vec[index].first().copied(),
+            ExprTerm::Json(vec) => vec.first().copied(),
+            ExprTerm::Values(vec) => vec[index].as_ref(),
+            ExprTerm::Computed(vec) => vec[index].first(),
+            _ => literal.as_ref(),
+        }
     }
 
-    fn cmp_json_number<C>(n2: &Number,
-                          fk1: &Option<FilterKey>,
-                          vec1: &[&'a Value],
-                          cmp_fn: &C) -> Vec<&'a Value>
+    // the number a term holds for a filter candidate, if it is a single number
+    fn number<'b>(&'b self, literal: &'b Option<Value>, index: usize) -> Option<&'b Number> {
+        match self.nodes(literal, index)[..] {
+            [Value::Number(n)] => Some(n),
+            _ => None,
+        }
+    }
+
+    // the number of filter candidates a per-candidate term holds
+    fn candidates_len(&self) -> Option<usize> {
+        match self {
+            ExprTerm::Relative(vec) => Some(vec.len()),
+            ExprTerm::Values(vec) => Some(vec.len()),
+            ExprTerm::Computed(vec) => Some(vec.len()),
+            _ => None,
+        }
+    }
+
+    fn cmp_literal<C>(&self, other: &ExprTerm<'a>, cmp_fn: &C) -> bool
         where
-            C: Cmp
+            C: Cmp,
     {
-        let n2 = utils::to_f64(n2);
-        vec1.iter().filter(|v1| match v1 {
-            Value::Number(n1) => cmp_fn.cmp_f64(utils::to_f64(n1), n2),
-            Value::Object(map1) => {
-                if let Some(FilterKey::String(k)) = fk1 {
-                    if let Some(Value::Number(n1)) = map1.get(*k) {
-                        return cmp_fn.cmp_f64(utils::to_f64(n1), n2);
-                    }
-                }
-                cmp_fn.default()
+        match (self, other) {
+            (ExprTerm::String(s1), ExprTerm::String(s2)) => {
+                let p1 = utils::to_path_str(s1);
+                let p2 = utils::to_path_str(s2);
+                cmp_fn.cmp_string(p1.get_key(), p2.get_key())
+            }
+            (ExprTerm::Number(n1), ExprTerm::Number(n2)) => cmp_fn.cmp_number(n1, n2),
+            (ExprTerm::Bool(b1), ExprTerm::Bool(b2)) => cmp_fn.cmp_bool(*b1, *b2),
+            (ExprTerm::Null, ExprTerm::Null) => cmp_fn.cmp_null(),
+            (ExprTerm::Literal(_), _) | (_, ExprTerm::Literal(_)) => {
+                cmp_fn.cmp_value(self.to_value().as_ref(), other.to_value().as_ref())
             }
             _ => cmp_fn.default(),
-        }).copied().collect()
+        }
     }
 
-    fn cmp_json_bool<C1>(b2: &bool,
-                         fk1: &Option<FilterKey>,
-                         vec1: &[&'a Value],
-                         cmp_fn: &C1) -> Vec<&'a Value>
+    fn cmp_value_literal<C>(v1: &Value, other: &ExprTerm<'a>, cmp_fn: &C) -> bool
         where
-            C1: Cmp
+            C: Cmp,
     {
-        vec1.iter().filter(|v1| match v1 {
-            Value::Bool(b1) => cmp_fn.cmp_bool(*b1, *b2),
-            Value::Object(map1) => {
-                if let Some(FilterKey::String(k)) = fk1 {
-                    if let Some(Value::Bool(b1)) = map1.get(*k) {
-                        return cmp_fn.cmp_bool(*b1, *b2);
-                    }
-                }
-                cmp_fn.default()
-            }
+        match (v1, other) {
+            (Value::String(s1), ExprTerm::String(s2)) => cmp_fn.cmp_string(s1, utils::to_path_str(s2).get_key()),
+            (Value::Number(n1), ExprTerm::Number(n2)) => cmp_fn.cmp_number(n1, n2),
+            (Value::Bool(b1), ExprTerm::Bool(b2)) => cmp_fn.cmp_bool(*b1, *b2),
+            (Value::Null, ExprTerm::Null) => cmp_fn.cmp_null(),
+            (_, ExprTerm::Literal(v2)) => cmp_fn.cmp_value(Some(v1), Some(v2)),
             _ => cmp_fn.default(),
-        }).copied().collect()
+        }
     }
 
-    fn cmp_json_json<C1>(rel: &Option<Vec<&'a Value>>,
-                         parent: &Option<Vec<&'a Value>>,
-                         vec1: &[&'a Value],
-                         vec2: &[&'a Value],
-                         cmp_fn: &C1) -> Vec<&'a Value>
+    fn cmp_nodes<C>(vec1: &[&'a Value], other: &ExprTerm<'a>, cmp_fn: &C) -> bool
         where
-            C1: Cmp
+            C: Cmp,
     {
-        if let Some(vec1) = rel {
-            if let Some(vec2) = parent {
-                cmp_fn.cmp_json(vec1, vec2)
-            } else {
-                cmp_fn.cmp_json(vec1, vec2)
-            }
-        } else if let Some(vec2) = parent {
-            cmp_fn.cmp_json(vec1, vec2)
-        } else {
-            cmp_fn.cmp_json(vec1, vec2)
+        match other {
+            ExprTerm::Json(vec2) => !cmp_fn.cmp_json(vec1, vec2).is_empty(),
+            _ => vec1.iter().any(|v1| Self::cmp_value_literal(v1, other, cmp_fn)),
         }
     }
 
-    fn cmp_json<C1>(rel: Option<Vec<&'a Value>>,
-                    fk1: Option<FilterKey<'a>>,
-                    vec1: &mut Vec<&'a Value>,
-                    other: &mut ExprTerm<'a>,
-                    cmp_fn: &C1) -> ExprTerm<'a>
+    //
+    // A node list matches a literal if any of its nodes does, two paths are
+    // compared node by node with `Cmp::cmp_json`, and an empty result falls back
+    // to `Cmp::default`.
+    //
+    fn cmp_default<C1, C2>(&self, other: &Self, cmp_fn: &C1, rev_cmp_fn: &C2) -> ExprTerm<'a>
         where
-            C1: Cmp
+            C1: Cmp,
+            C2: Cmp,
     {
-        let ret: Vec<&Value> = match other {
-            ExprTerm::String(s2) => Self::cmp_json_string(s2, &fk1, vec1, cmp_fn),
-            ExprTerm::Number(n2) => Self::cmp_json_number(n2, &fk1, vec1, cmp_fn),
-            ExprTerm::Bool(b2) => Self::cmp_json_bool(b2, &fk1, vec1, cmp_fn),
-            ExprTerm::Json(parent, _, vec2) => {
-                Self::cmp_json_json(&rel, parent, vec1, vec2, cmp_fn)
-            }
+        let matched: Vec<usize> = match (self, other) {
+            (ExprTerm::Relative(vec1), ExprTerm::Relative(vec2)) => (0..vec1.len())
+                .filter(|i| !cmp_fn.cmp_json(&vec1[*i], &vec2[*i]).is_empty())
+                .collect(),
+            (ExprTerm::Relative(vec1), _) => (0..vec1.len())
+                .filter(|i| Self::cmp_nodes(&vec1[*i], other, cmp_fn))
+                .collect(),
+            (_, ExprTerm::Relative(vec2)) => (0..vec2.len())
+                .filter(|i| Self::cmp_nodes(&vec2[*i], self, rev_cmp_fn))
+                .collect(),
+            (ExprTerm::Json(vec1), _) => return ExprTerm::Bool(Self::cmp_nodes(vec1, other, cmp_fn)),
+            (_, ExprTerm::Json(vec2)) => return ExprTerm::Bool(Self::cmp_nodes(vec2, self, rev_cmp_fn)),
+            _ => return ExprTerm::Bool(self.cmp_literal(other, cmp_fn)),
         };
 
-        if ret.is_empty() {
+        if matched.is_empty() {
             return ExprTerm::Bool(cmp_fn.default());
         }
 
-        if rel.is_none() {
-            return ExprTerm::Json(None, None, ret);
-        }
+        ExprTerm::Matched(matched)
+    }
 
-        if rel.is_some() {
-            if let ExprTerm::Json(_, _, _) = &other {
-                if let Some(rel) = rel {
-                    return ExprTerm::Json(Some(rel), None, ret);
-                }
-            }
+    //
+    // RFC 9535 2.3.5.2.2: each side is a single value, or nothing.
+    //
+    fn cmp_rfc9535<C>(&self, other: &Self, cmp_fn: &C) -> ExprTerm<'a>
+        where
+            C: Cmp,
+    {
+        let literal1 = self.to_value();
+        let literal2 = other.to_value();
+
+        match self.candidates_len().or_else(|| other.candidates_len()) {
+            Some(len) => ExprTerm::Matched((0..len).filter(|i| {
+                cmp_fn.cmp_value(self.operand(&literal1, *i), other.operand(&literal2, *i))
+            }).collect()),
+            _ => ExprTerm::Bool(cmp_fn.cmp_value(self.operand(&literal1, 0), other.operand(&literal2, 0))),
         }
+    }
 
-        let rel = rel.unwrap();
-        let mut object_exist = false;
-        for v in &rel {
-            if v.is_object() {
-                object_exist = true;
-                break;
-            }
-        }
+    //
+    // The set of a membership operator is an array, given inline or as the single
+    // node of a path, or else the nodes themselves. The left operand holds if any
+    // of its nodes does.
+    //
+    fn cmp_members<C>(&self, other: &Self, cmp_fn: &C) -> ExprTerm<'a>
+        where
+            C: Cmp,
+    {
+        let literal1 = self.to_value();
+        let literal2 = other.to_value();
+        let holds = |i: usize| {
+            let set = other.nodes(&literal2, i);
+            let members = match set.as_slice() {
+                [Value::Array(vec)] => vec.iter().collect(),
+                _ => set,
+            };
+            self.nodes(&literal1, i).iter().any(|v| cmp_fn.cmp_members(v, &members))
+        };
 
-        if !object_exist {
-            return ExprTerm::Json(Some(Vec::new()), None, ret);
+        match self.candidates_len().or_else(|| other.candidates_len()) {
+            Some(len) => ExprTerm::Matched((0..len).filter(|i| holds(*i)).collect()),
+            _ => ExprTerm::Bool(holds(0)),
         }
+    }
 
-        let ret_set: HashSet<*const Value> = ret.iter()
-            .fold(HashSet::new(), |mut acc, v| {
-                let ptr = *v as *const Value;
-                acc.insert(ptr);
-                acc
-            });
-
-        let mut tmp = Vec::new();
-        for rv in rel {
-            if let Value::Object(map) = rv {
-                for map_value in map.values() {
-                    let ptr = map_value as *const Value;
-                    if ret_set.contains(&ptr) {
-                        tmp.push(rv);
-                    }
-                }
-            }
-        }
+    // computed values compare like the nodes of a path
+    fn cmp_computed<C>(&self, other: &Self, cmp_fn: &C) -> ExprTerm<'a>
+        where
+            C: Cmp,
+    {
+        let literal1 = self.to_value();
+        let literal2 = other.to_value();
+        let len = self.candidates_len().or_else(|| other.candidates_len()).unwrap_or_default();
 
-        ExprTerm::Json(Some(tmp), None, ret)
+        ExprTerm::Matched((0..len).filter(|i| {
+            !cmp_fn.cmp_json(&self.nodes(&literal1, *i), &other.nodes(&literal2, *i)).is_empty()
+        }).collect())
     }
 
-    fn cmp<C1, C2>(&mut self, other: &mut Self, cmp_fn: &C1, rev_cmp_fn: &C2) -> ExprTerm<'a>
+    fn cmp<C1, C2>(&self, other: &Self, mode: ParserMode, cmp_fn: &C1, rev_cmp_fn: &C2) -> ExprTerm<'a>
         where
             C1: Cmp,
             C2: Cmp
     {
-        if let ExprTerm::Json(_, _, _) = other {
-            if let ExprTerm::Json(_, _, _) = &self {
-                //
-            } else {
-                return other.cmp(self, rev_cmp_fn, cmp_fn);
-            }
+        if matches!(self, ExprTerm::Computed(_)) || matches!(other, ExprTerm::Computed(_)) {
+            return self.cmp_computed(other, cmp_fn);
         }
 
-        match self {
-            ExprTerm::String(s1) => Self::cmp_string(s1, other, cmp_fn),
-            ExprTerm::Number(n1) => Self::cmp_number(n1, other, cmp_fn),
-            ExprTerm::Bool(b1) => Self::cmp_bool(b1, other, cmp_fn),
-            ExprTerm::Json(rel, fk1, vec1) =>
-                Self::cmp_json(rel.take(), fk1.take(), vec1, other, cmp_fn)
+        let is_function = matches!(self, ExprTerm::Values(_)) || matches!(other, ExprTerm::Values(_));
+        match mode {
+            // function results follow the RFC 9535 rules in either mode
+            ParserMode::Default if !is_function => self.cmp_default(other, cmp_fn, rev_cmp_fn),
+            _ => self.cmp_rfc9535(other, cmp_fn),
         }
     }
 
-    pub fn eq_(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn eq_(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("eq - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpEq, &CmpEq);
+        let expr = self.cmp(&other, mode, &CmpEq, &CmpEq);
         debug!("eq = {:?}", expr);
         expr
     }
 
-    pub fn ne_(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn ne_(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("ne - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpNe, &CmpNe);
+        let expr = self.cmp(&other, mode, &CmpNe, &CmpNe);
         debug!("ne = {:?}", expr);
         expr
     }
 
-    pub fn gt(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn gt(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("gt - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpGt, &CmpLt);
+        let expr = self.cmp(&other, mode, &CmpGt, &CmpLt);
         debug!("gt = {:?}", expr);
         expr
     }
 
-    pub fn ge(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn ge(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("ge - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpGe, &CmpLe);
+        let expr = self.cmp(&other, mode, &CmpGe, &CmpLe);
         debug!("ge = {:?}", expr);
         expr
     }
 
-    pub fn lt(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn lt(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("lt - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpLt, &CmpGt);
+        let expr = self.cmp(&other, mode, &CmpLt, &CmpGt);
         debug!("lt = {:?}", expr);
         expr
     }
 
-    pub fn le(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn le(&self, other: Self, mode: ParserMode) -> ExprTerm<'a> {
         debug!("le - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpLe, &CmpGe);
+        let expr = self.cmp(&other, mode, &CmpLe, &CmpGe);
         debug!("le = {:?}", expr);
         expr
     }
 
-    pub fn and(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn in_(&self, other: Self) -> ExprTerm<'a> {
+        debug!("in - {:?} : {:?}", &self, &other);
+        let expr = self.cmp_members(&other, &CmpIn);
+        debug!("in = {:?}", expr);
+        expr
+    }
+
+    pub fn nin(&self, other: Self) -> ExprTerm<'a> {
+        debug!("nin - {:?} : {:?}", &self, &other);
+        let expr = self.cmp_members(&other, &CmpNin);
+        debug!("nin = {:?}", expr);
+        expr
+    }
+
+    pub fn subset_of(&self, other: Self) -> ExprTerm<'a> {
+        debug!("subsetof - {:?} : {:?}", &self, &other);
+        let expr = self.cmp_members(&other, &CmpSubsetOf);
+        debug!("subsetof = {:?}", expr);
+        expr
+    }
+
+    pub fn any_of(&self, other: Self) -> ExprTerm<'a> {
+        debug!("anyof - {:?} : {:?}", &self, &other);
+        let expr = self.cmp_members(&other, &CmpAnyOf);
+        debug!("anyof = {:?}", expr);
+        expr
+    }
+
+    pub fn none_of(&self, other: Self) -> ExprTerm<'a> {
+        debug!("noneof - {:?} : {:?}", &self, &other);
+        let expr = self.cmp_members(&other, &CmpNoneOf);
+        debug!("noneof = {:?}", expr);
+        expr
+    }
+
+    //
+    // Strings the regex finds a match in. Like the other comparisons, a node list
+    // matches if any of its nodes does.
+    //
+    pub fn matches(&self, other: Self) -> ExprTerm<'a> {
+        debug!("matches - {:?} : {:?}", &self, &other);
+        let regex = match &other {
+            ExprTerm::Regex(RegexTerm(regex)) => regex,
+            _ => return ExprTerm::Bool(false),
+        };
+
+        let is_match = |v: &&Value| matches!(v, Value::String(s) if regex.is_match(s));
+        let expr = match self {
+            ExprTerm::Relative(vec) => ExprTerm::Matched(
+                (0..vec.len()).filter(|i| vec[*i].iter().any(is_match)).collect()
+            ),
+            ExprTerm::Json(vec) => ExprTerm::Bool(vec.iter().any(is_match)),
+            ExprTerm::Values(vec) => ExprTerm::Matched(
+                (0..vec.len()).filter(|i| vec[*i].iter().any(|v| is_match(&v))).collect()
+            ),
+            ExprTerm::Computed(vec) => ExprTerm::Matched(
+                (0..vec.len()).filter(|i| vec[*i].iter().any(|v| is_match(&v))).collect()
+            ),
+            ExprTerm::String(s) => ExprTerm::Bool(regex.is_match(utils::to_path_str(s).get_key())),
+            _ => ExprTerm::Bool(false),
+        };
+        debug!("matches = {:?}", expr);
+        expr
+    }
+
+    /// Calls a function extension once for each of `len` filter candidates.
+    pub fn call(function: &FunctionToken, args: Vec<ExprTerm<'a>>, len: usize) -> ExprTerm<'a> {
+        debug!("call - {:?} : {:?}", function, args);
+        let literals: Vec<Option<Value>> = args.iter().map(|arg| arg.to_value()).collect();
+        let value = |arg: usize, i: usize| args[arg].operand(&literals[arg], i);
+        let nodes = |arg: usize, i: usize| args[arg].nodes(&literals[arg], i);
+
+        let mut matcher = RegexMatcher::default();
+        let expr = match function {
+            FunctionToken::Length => ExprTerm::Values((0..len).map(|i| functions::length(value(0, i))).collect()),
+            FunctionToken::Count => ExprTerm::Values((0..len).map(|i| functions::count(&nodes(0, i))).collect()),
+            FunctionToken::Value => ExprTerm::Values((0..len).map(|i| functions::value(&nodes(0, i))).collect()),
+            FunctionToken::Match => ExprTerm::Matched(
+                (0..len).filter(|i| matcher.is_match(value(0, *i), value(1, *i), true)).collect()
+            ),
+            FunctionToken::Search => ExprTerm::Matched(
+                (0..len).filter(|i| matcher.is_match(value(0, *i), value(1, *i), false)).collect()
+            ),
+            FunctionToken::Custom { .. } => unreachable!("a registered function is called with call_custom"),
+        };
+        debug!("call = {:?}", expr);
+        expr
+    }
+
+    /// Calls a function of a `FunctionRegistry` once for each of `len` filter candidates.
+    pub fn call_custom(function: &CustomFunction, args: Vec<ExprTerm<'a>>, len: usize) -> ExprTerm<'a> {
+        debug!("call_custom - {:?}", args);
+        let parameters = function.parameters();
+        let args: Vec<ExprTerm<'a>> = args.into_iter().zip(parameters)
+            .map(|(arg, param)| match param {
+                FunctionType::Logical => arg.into_logical(),
+                _ => arg,
+            })
+            .collect();
+        let literals: Vec<Option<Value>> = args.iter().map(|arg| arg.to_value()).collect();
+        let arguments = |i: usize| -> Vec<FunctionArgument> {
+            parameters.iter().enumerate().map(|(arg, param)| match param {
+                FunctionType::Value => FunctionArgument::Value(args[arg].operand(&literals[arg], i)),
+                FunctionType::Nodes => FunctionArgument::Nodes(args[arg].nodes(&literals[arg], i)),
+                FunctionType::Logical => FunctionArgument::Logical(match &args[arg] {
+                    ExprTerm::Matched(indices) => indices.contains(&i),
+                    ExprTerm::Bool(b) => *b,
+                    _ => true,
+                }),
+            }).collect()
+        };
+
+        let expr = match function.body() {
+            FunctionBody::Value(fun) => ExprTerm::Values((0..len).map(|i| fun(&arguments(i))).collect()),
+            FunctionBody::Logical(fun) => ExprTerm::Matched((0..len).filter(|i| fun(&arguments(*i))).collect()),
+        };
+        debug!("call_custom = {:?}", expr);
+        expr
+    }
+
+    /// `self op other` for each of `len` filter candidates. Only single numbers are
+    /// operands: anything else, like a division by zero, gives no value.
+    pub fn arithmetic(self, token: &ArithmeticToken, other: Self, len: usize) -> ExprTerm<'a> {
+        debug!("arithmetic - {:?} {:?} {:?}", &self, token, &other);
+        let (l1, l2) = (self.to_value(), other.to_value());
+        let expr = ExprTerm::Values((0..len).map(|i| match (self.number(&l1, i), other.number(&l2, i)) {
+            (Some(n1), Some(n2)) => utils::arithmetic(token, n1, n2).map(Value::Number),
+            _ => None,
+        }).collect());
+        debug!("arithmetic = {:?}", expr);
+        expr
+    }
+
+    /// The truth value of a term: `Bool`, or `Matched` for the candidates it holds for.
+    pub fn into_logical(self) -> ExprTerm<'a> {
+        match self {
+            ExprTerm::Relative(vec) => ExprTerm::Matched(
+                vec.iter().enumerate().filter(|(_, nodes)| !nodes.is_empty()).map(|(i, _)| i).collect()
+            ),
+            ExprTerm::Json(vec) => ExprTerm::Bool(!vec.is_empty()),
+            ExprTerm::Values(vec) => ExprTerm::Matched(
+                vec.iter().enumerate().filter(|(_, v)| v.is_some()).map(|(i, _)| i).collect()
+            ),
+            ExprTerm::Computed(vec) => ExprTerm::Matched(
+                vec.iter().enumerate().filter(|(_, computed)| !computed.is_empty()).map(|(i, _)| i).collect()
+            ),
+            ExprTerm::Matched(indices) => ExprTerm::Matched(indices),
+            ExprTerm::Bool(b) => ExprTerm::Bool(b),
+            _ => ExprTerm::Bool(true),
+        }
+    }
+
+    pub fn and(self, other: Self) -> ExprTerm<'a> {
         debug!("and - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpAnd, &CmpAnd);
+        let expr = match (self.into_logical(), other.into_logical()) {
+            (ExprTerm::Bool(b1), ExprTerm::Bool(b2)) => ExprTerm::Bool(CmpAnd.cmp_bool(b1, b2)),
+            (ExprTerm::Bool(true), e) | (e, ExprTerm::Bool(true)) => e,
+            (ExprTerm::Matched(m1), ExprTerm::Matched(m2)) => {
+                let set: HashSet<usize> = m2.into_iter().collect();
+                ExprTerm::Matched(m1.into_iter().filter(|i| set.contains(i)).collect())
+            }
+            _ => ExprTerm::Bool(false),
+        };
         debug!("and = {:?}", expr);
         expr
     }
 
-    pub fn or(&mut self, mut other: Self) -> ExprTerm<'a> {
+    pub fn or(self, other: Self) -> ExprTerm<'a> {
         debug!("or - {:?} : {:?}", &self, &other);
-        let expr = self.cmp(&mut other, &CmpOr, &CmpOr);
+        let expr = match (self.into_logical(), other.into_logical()) {
+            (ExprTerm::Bool(b1), ExprTerm::Bool(b2)) => ExprTerm::Bool(CmpOr.cmp_bool(b1, b2)),
+            (ExprTerm::Bool(false), e) | (e, ExprTerm::Bool(false)) => e,
+            (ExprTerm::Matched(mut m1), ExprTerm::Matched(m2)) => {
+                let set: HashSet<usize> = m1.iter().copied().collect();
+                m1.extend(m2.into_iter().filter(|i| !set.contains(i)));
+                // the candidates stay in the order they are filtered in
+                m1.sort_unstable();
+                ExprTerm::Matched(m1)
+            }
+            _ => ExprTerm::Bool(true),
+        };
         debug!("or = {:?}", expr);
         expr
     }
+
+    /// `!`: the candidates, out of `len`, that the term does not hold for.
+    pub fn not(self, len: usize) -> ExprTerm<'a> {
+        debug!("not - {:?}", &self);
+        let expr = match self.into_logical() {
+            ExprTerm::Matched(indices) => {
+                let set: HashSet<usize> = indices.into_iter().collect();
+                ExprTerm::Matched((0..len).filter(|i| !set.contains(i)).collect())
+            }
+            ExprTerm::Bool(b) => ExprTerm::Bool(!b),
+            _ => ExprTerm::Bool(false),
+        };
+        debug!("not = {:?}", expr);
+        expr
+    }
 }
 
 impl<'a> From<&Vec<&'a Value>> for ExprTerm<'a> {
@@ -294,195 +509,94 @@ impl<'a> From<&Vec<&'a Value>> for ExprTerm<'a> {
                 Value::Number(v) => return ExprTerm::Number(v.clone()),
                 Value::String(v) => return ExprTerm::String(v.as_str()),
                 Value::Bool(v) => return ExprTerm::Bool(*v),
+                Value::Null => return ExprTerm::Null,
                 _ => {}
             }
         }
 
-        ExprTerm::Json(None, None, vec.to_vec())
+        ExprTerm::Json(vec.to_vec())
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum FilterKey<'a> {
-    String(&'a str),
-    All,
-}
-
-struct FilterResult<'a> {
-    key: FilterKey<'a>,
-    collected: Vec<&'a Value>
-}
-
 #[derive(Debug, Default)]
-pub struct FilterTerms<'a>(pub Vec<Option<ExprTerm<'a>>>);
+pub struct FilterTerms<'a> {
+    terms: Vec<ExprTerm<'a>>,
+    candidates: Option<Vec<&'a Value>>,
+}
 
 impl<'a> FilterTerms<'a> {
-    pub fn new_filter_context(&mut self) {
-        self.0.push(None);
-        debug!("new_filter_context: {:?}", self.0);
-    }
-
     pub fn is_term_empty(&self) -> bool {
-        self.0.is_empty()
+        self.terms.is_empty()
     }
 
-    pub fn push_term(&mut self, term: Option<ExprTerm<'a>>) {
-        self.0.push(term);
+    pub fn push_term(&mut self, term: ExprTerm<'a>) {
+        self.terms.push(term);
     }
 
-    #[allow(clippy::option_option)]
-    pub fn pop_term(&mut self) -> Option<Option<ExprTerm<'a>>> {
-        self.0.pop()
+    pub fn pop_term(&mut self) -> Option<ExprTerm<'a>> {
+        self.terms.pop()
     }
 
-    fn filter_json_term<F>(&mut self, e: ExprTerm<'a>, fun: F)
-        where
-            F: Fn(&Vec<&'a Value>, &mut Option<HashSet<usize>>) -> FilterResult<'a>,
-    {
-        debug!("filter_json_term: {:?}", e);
-
-        if let ExprTerm::Json(rel, fk, vec) = e {
-            let mut not_matched = Some(HashSet::new());
-            let filter_result = if let Some(FilterKey::String(key)) = fk {
-                fun(&ValueWalker::next_with_str(&vec, key), &mut not_matched)
-            } else {
-                fun(&vec, &mut not_matched)
-            };
-
-            if rel.is_some() {
-                self.push_term(Some(ExprTerm::Json(
-                    rel,
-                    Some(filter_result.key),
-                    filter_result.collected)));
-            } else {
-                let not_matched = not_matched.unwrap();
-                let filtered = vec.iter().enumerate()
-                    .filter(|(idx, _)| !not_matched.contains(idx))
-                    .map(|(_, v)| *v).collect();
-                self.push_term(Some(ExprTerm::Json(
-                    Some(filtered),
-                    Some(filter_result.key),
-                    filter_result.collected)));
-            }
-        } else {
-            unreachable!("unexpected: ExprTerm: {:?}", e);
-        }
+    pub fn has_candidates(&self) -> bool {
+        self.candidates.is_some()
     }
 
-    fn push_json_term<F>(&mut self, current: Option<Vec<&'a Value>>, fun: F) -> Option<Vec<&'a Value>>
-        where
-            F: Fn(&Vec<&'a Value>, &mut Option<HashSet<usize>>) -> FilterResult<'a>,
-    {
-        debug!("push_json_term: {:?}", &current);
-
-        if let Some(current) = &current {
-            let filter_result = fun(current, &mut None);
-            self.push_term(Some(ExprTerm::Json(
-                None,
-                Some(filter_result.key),
-                filter_result.collected)));
-        }
-
-        current
+    pub fn candidates(&self) -> &[&'a Value] {
+        self.candidates.as_ref().map_or(&[], |v| v.as_slice())
     }
 
-    fn filter<F>(&mut self, current: Option<Vec<&'a Value>>, fun: F) -> Option<Vec<&'a Value>>
-        where
-            F: Fn(&Vec<&'a Value>, &mut Option<HashSet<usize>>) -> FilterResult<'a>,
-    {
-        let peek = self.pop_term();
+    pub fn set_candidates(&mut self, candidates: Vec<&'a Value>) {
+        self.candidates = Some(candidates);
+    }
 
-        if let Some(None) = peek {
-            return self.push_json_term(current, fun);
-        }
+    pub fn take_candidates(&mut self) -> Vec<&'a Value> {
+        self.candidates.take().unwrap_or_default()
+    }
 
-        if let Some(Some(e)) = peek {
-            self.filter_json_term(e, fun);
+    pub fn collect_next_with_num(&mut self, current: Option<Vec<&'a Value>>, index: f64) -> Option<Vec<&'a Value>> {
+        if current.is_none() {
+            debug!("collect_next_with_num : {:?}, {:?}", &index, &current);
+            return current;
         }
 
-        current
+        Some(ValueWalker::next_with_num(&current.unwrap(), index))
     }
 
-    pub fn filter_all_with_str(&mut self, current: Option<Vec<&'a Value>>, key: &'a str) -> Option<Vec<&'a Value>> {
-        let current = self.filter(current, |vec, _| {
-            FilterResult {
-                key: FilterKey::All,
-                collected: ValueWalker::all_with_str(vec, key)
-            }
-        });
-
-        debug!("filter_all_with_str : {}, {:?}", key, self.0);
-        current
-    }
-
-    pub fn filter_next_with_str(&mut self, current: Option<Vec<&'a Value>>, key: &'a str) -> Option<Vec<&'a Value>> {
-        let current = self.filter(current, |vec, not_matched| {
-            let mut visited = HashSet::new();
-            let mut acc = Vec::new();
-
-            let path_key = &utils::to_path_str(key);
-
-            ValueWalker::walk_dedup_all(vec,
-                                        path_key.get_key(),
-                                        &mut visited,
-                                        &mut |v| {
-                                            acc.push(v);
-                                        },
-                                        &mut |idx| {
-                                            if let Some(set) = not_matched {
-                                                set.insert(idx);
-                                            }
-                                        },
-                                        0
-            );
-
-            FilterResult {
-                key: FilterKey::String(path_key.get_origin_key()),
-                collected: acc
-            }
-        });
+    pub fn collect_next_with_index(&mut self, current: Option<Vec<&'a Value>>, index: isize) -> Option<Vec<&'a Value>> {
+        if current.is_none() {
+            debug!("collect_next_with_index : {:?}, {:?}", &index, &current);
+            return current;
+        }
 
-        debug!("filter_next_with_str : {}, {:?}", key, self.0);
-        current
+        Some(ValueWalker::next_with_index(&current.unwrap(), index))
     }
 
-    pub fn collect_next_with_num(&mut self, current: Option<Vec<&'a Value>>, index: f64) -> Option<Vec<&'a Value>> {
+    pub fn collect_next_with_range(&mut self,
+                                   current: Option<Vec<&'a Value>>,
+                                   from: Option<isize>,
+                                   to: Option<isize>,
+                                   step: Option<isize>) -> Option<Vec<&'a Value>> {
         if current.is_none() {
-            debug!("collect_next_with_num : {:?}, {:?}", &index, &current);
+            debug!("collect_next_with_range : {:?}, {:?}, {:?}, {:?}", from, to, step, &current);
             return current;
         }
 
-        if let Some(Some(e)) = self.pop_term() {
-            match e {
-                ExprTerm::Json(rel, _, vec) => {
-                    return if vec.is_empty() {
-                        Some(Vec::new())
-                    } else if let Some(vec) = rel {
-                        let index = utils::abs_index(index as isize, vec.len());
-                        let ret = vec.get(index).map_or(Vec::new(), |v| vec![*v]);
-                        Some(ret)
-                    } else {
-                        let index = utils::abs_index(index as isize, vec.len());
-                        let ret = vec.get(index).map_or(Vec::new(), |v| vec![*v]);
-                        Some(ret)
-                    };
-                }
-                _ => {
-                    self.push_term(Some(e));
-                }
-            }
-        }
-
-        let acc = ValueWalker::next_with_num(&current.unwrap(), index);
+        Some(ValueWalker::next_with_range(&current.unwrap(), from, to, step))
+    }
 
-        if acc.is_empty() {
-            self.pop_term();
+    pub fn collect_next_with_key_pattern(&mut self, current: Option<Vec<&'a Value>>, regex: &Regex) -> Option<Vec<&'a Value>> {
+        if current.is_none() {
+            debug!("collect_next_with_key_pattern : {:?}, {:?}", regex, &current);
+            return current;
         }
 
-        Some(acc)
+        Some(ValueWalker::next_with_key_pattern(&current.unwrap(), regex))
     }
 
-    pub fn collect_next_with_str(&mut self, current: Option<Vec<&'a Value>>, keys: &[&'a str]) -> Option<Vec<&'a Value>> {
+    pub fn collect_next_with_str(&mut self,
+                                 current: Option<Vec<&'a Value>>,
+                                 keys: &[&'a str],
+                                 key_match: KeyMatch) -> Option<Vec<&'a Value>> {
         if current.is_none() {
             debug!(
                 "collect_next_with_str : {:?}, {:?}",
@@ -491,13 +605,7 @@ impl<'a> FilterTerms<'a> {
             return current;
         }
 
-        let acc = ValueWalker::all_with_strs(current.as_ref().unwrap(), keys);
-
-        if acc.is_empty() {
-            self.pop_term();
-        }
-
-        Some(acc)
+        Some(ValueWalker::all_with_strs(current.as_ref().unwrap(), keys, key_match))
     }
 
     pub fn collect_next_all(&mut self, current: Option<Vec<&'a Value>>) -> Option<Vec<&'a Value>> {
@@ -509,36 +617,28 @@ impl<'a> FilterTerms<'a> {
         Some(ValueWalker::next_all(&current.unwrap()))
     }
 
-    pub fn collect_all(&mut self, current: Option<Vec<&'a Value>>) -> Option<Vec<&'a Value>> {
+    pub fn collect_all(&mut self, current: Option<Vec<&'a Value>>, descent: Descent) -> Option<Vec<&'a Value>> {
         if current.is_none() {
             debug!("collect_all: {:?}", &current);
             return current;
         }
 
-        Some(ValueWalker::all(current.as_ref().unwrap()))
+        Some(ValueWalker::all(current.as_ref().unwrap(), descent))
     }
 
-    pub fn collect_all_with_str(&mut self, current: Option<Vec<&'a Value>>, key: &'a str) -> Option<Vec<&'a Value>> {
+    pub fn collect_all_with_str(&mut self,
+                                current: Option<Vec<&'a Value>>,
+                                key: &'a str,
+                                descent: Descent,
+                                key_match: KeyMatch) -> Option<Vec<&'a Value>> {
         if current.is_none() {
             debug!("collect_all_with_str: {}, {:?}", key, &current);
             return current;
         }
 
-        let ret = ValueWalker::all_with_str(current.as_ref().unwrap(), key);
+        let ret = ValueWalker::all_with_str(current.as_ref().unwrap(), key, descent, key_match);
         Some(ret)
     }
-
-    pub fn collect_all_with_num(&mut self, mut current: Option<Vec<&'a Value>>, index: f64) -> Option<Vec<&'a Value>> {
-        if let Some(current) = current.take() {
-            let ret = ValueWalker::all_with_num(&current, index);
-            if !ret.is_empty() {
-                return Some(ret);
-            }
-        }
-
-        debug!("collect_all_with_num: {}, {:?}", index, &current);
-        None
-    }
 }
 
 #[cfg(test)]
@@ -564,4 +664,20 @@ mod expr_term_inner_tests {
         let term: ExprTerm = vec.into();
         assert_eq!(term, ExprTerm::Number(Number::from_f64(1.0).unwrap()));
     }
+
+    #[test]
+    fn logical_ops() {
+        let matched = |v: Vec<usize>| ExprTerm::Matched(v);
+
+        assert_eq!(matched(vec![2, 0]).and(matched(vec![0, 1])), matched(vec![0]));
+        assert_eq!(matched(vec![2, 0]).or(matched(vec![0, 1])), matched(vec![0, 1, 2]));
+        assert_eq!(ExprTerm::Bool(true).and(matched(vec![1])), matched(vec![1]));
+        assert_eq!(ExprTerm::Bool(false).and(matched(vec![1])), ExprTerm::Bool(false));
+        assert_eq!(ExprTerm::Bool(true).or(matched(vec![1])), ExprTerm::Bool(true));
+        assert_eq!(ExprTerm::Bool(false).or(matched(vec![1])), matched(vec![1]));
+
+        let v = Value::Null;
+        let relative = ExprTerm::Relative(vec![vec![], vec![&v]]);
+        assert_eq!(relative.into_logical(), matched(vec![1]));
+    }
 }
@@ -1,13 +1,157 @@
-use serde_json::Number;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use caseless;
+use regex::{Regex, RegexBuilder};
+use serde_json::{Number, Value};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+use paths::tokens::ArithmeticToken;
+use paths::unescape;
+use super::selector_impl::KeyMatch;
+
+// upper bound of a compiled pattern, the regex crate itself matches in linear time
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Builds a regex of a path, a filter or a function argument within the size limits.
+pub fn build_regex(builder: &mut RegexBuilder) -> Option<Regex> {
+    builder
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .ok()
+}
 
 pub fn to_f64(n: &Number) -> f64 {
-    if n.is_i64() {
-        n.as_i64().unwrap() as f64
-    } else if n.is_f64() {
-        n.as_f64().unwrap()
-    } else {
-        n.as_u64().unwrap() as f64
+    // with `arbitrary_precision` a number beyond f64 parses to an infinity
+    n.as_f64().unwrap_or(f64::NAN)
+}
+
+// compares integers exactly, and an integer with a float by its whole and fractional parts
+#[cfg(not(feature = "arbitrary_precision"))]
+pub fn cmp_number(n1: &Number, n2: &Number) -> Ordering {
+    fn to_i128(n: &Number) -> Option<i128> {
+        n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from))
     }
+
+    // a float beyond the range of i128 saturates, which still orders it after every integer
+    fn cmp_int_float(i: i128, f: f64) -> Ordering {
+        let whole = f.trunc();
+        i.cmp(&(whole as i128)).then_with(|| 0_f64.partial_cmp(&(f - whole)).unwrap_or(Ordering::Equal))
+    }
+
+    match (to_i128(n1), to_i128(n2)) {
+        (Some(i1), Some(i2)) => i1.cmp(&i2),
+        (Some(i1), None) => cmp_int_float(i1, to_f64(n2)),
+        (None, Some(i2)) => cmp_int_float(i2, to_f64(n1)).reverse(),
+        // no JSON number is NaN
+        (None, None) => to_f64(n1).partial_cmp(&to_f64(n2)).unwrap_or(Ordering::Equal),
+    }
+}
+
+// compares the decimal literals, which `arbitrary_precision` keeps as written
+#[cfg(feature = "arbitrary_precision")]
+pub fn cmp_number(n1: &Number, n2: &Number) -> Ordering {
+    Decimal::parse(&n1.to_string()).cmp(&Decimal::parse(&n2.to_string()))
+}
+
+// a JSON number as `0.digits * 10^exponent`, without leading or trailing zeros in `digits`
+#[cfg(feature = "arbitrary_precision")]
+#[derive(PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+#[cfg(feature = "arbitrary_precision")]
+impl Decimal {
+    fn parse(s: &str) -> Self {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(i) => {
+                let exponent = &s[i + 1..];
+                let saturated = if exponent.starts_with('-') { i64::MIN / 2 } else { i64::MAX / 2 };
+                (&s[..i], exponent.parse::<i64>().unwrap_or(saturated).clamp(i64::MIN / 2, i64::MAX / 2))
+            }
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", int, frac);
+        let leading = digits.len() - digits.trim_start_matches('0').len();
+        let digits = digits.trim_matches('0').to_string();
+        if digits.is_empty() {
+            return Decimal { negative: false, digits, exponent: 0 };
+        }
+        Decimal { negative, digits, exponent: exponent + int.len() as i64 - leading as i64 }
+    }
+
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        match (self.digits.is_empty(), other.digits.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => self.exponent.cmp(&other.exponent).then_with(|| self.digits.cmp(&other.digits)),
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary_precision")]
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary_precision")]
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// a whole number, also one held as a float like `2.0`
+pub fn to_i64(n: &Number) -> Option<i64> {
+    const MAX_EXACT: f64 = 9_007_199_254_740_992_f64;
+    n.as_i64().or_else(|| n.as_f64().filter(|f| f.fract() == 0_f64 && f.abs() <= MAX_EXACT).map(|f| f as i64))
+}
+
+// integers stay exact unless the result overflows or a division leaves a fraction
+pub fn arithmetic(token: &ArithmeticToken, n1: &Number, n2: &Number) -> Option<Number> {
+    if let (Some(i1), Some(i2)) = (to_i64(n1), to_i64(n2)) {
+        let ret = match token {
+            ArithmeticToken::Add => i1.checked_add(i2),
+            ArithmeticToken::Subtract => i1.checked_sub(i2),
+            ArithmeticToken::Multiply => i1.checked_mul(i2),
+            ArithmeticToken::Divide if i1.checked_rem(i2) == Some(0) => i1.checked_div(i2),
+            ArithmeticToken::Divide => None,
+            ArithmeticToken::Remainder => i1.checked_rem(i2),
+        };
+        if let Some(ret) = ret {
+            return Some(ret.into());
+        }
+    }
+
+    let (f1, f2) = (to_f64(n1), to_f64(n2));
+    let ret = match token {
+        ArithmeticToken::Add => f1 + f2,
+        ArithmeticToken::Subtract => f1 - f2,
+        ArithmeticToken::Multiply => f1 * f2,
+        ArithmeticToken::Divide => f1 / f2,
+        ArithmeticToken::Remainder => f1 % f2,
+    };
+    // a division by zero is infinite or NaN, which no JSON number holds
+    Number::from_f64(ret)
 }
 
 pub fn abs_index(n: isize, len: usize) -> usize {
@@ -18,6 +162,41 @@ pub fn abs_index(n: isize, len: usize) -> usize {
     }
 }
 
+// RFC 9535 2.3.3.2: a negative index counts from the end, out of range is nothing
+pub fn normalize_index(n: isize, len: usize) -> Option<usize> {
+    let index = if n < 0_isize { n + len as isize } else { n };
+    if index >= 0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+// RFC 9535 2.3.4.2: the indices selected by `[from:to:step]`, in the order of the step
+pub fn slice_indices(from: Option<isize>, to: Option<isize>, step: Option<isize>, len: usize) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let len = len as isize;
+    let normalize = |n: isize| if n < 0 { n + len } else { n };
+
+    if step > 0 {
+        let lower = from.map_or(0, normalize).max(0).min(len);
+        let upper = to.map_or(len, normalize).max(0).min(len);
+        (lower..upper).step_by(step as usize).map(|i| i as usize).collect()
+    } else if step < 0 {
+        let upper = from.map_or(len - 1, normalize).max(-1).min(len - 1);
+        let lower = to.map_or(-1, normalize).max(-1).min(len - 1);
+        let mut indices = Vec::new();
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i += step;
+        }
+        indices
+    } else {
+        Vec::new()
+    }
+}
+
 pub struct PathKey<'a> {
     key: &'a str,
     special_key: Option<String>
@@ -31,13 +210,9 @@ impl<'a: 'b, 'b> PathKey<'a> {
             self.key
         }
     }
-
-    pub fn get_origin_key(&self) -> &'a str {
-        self.key
-    }
 }
 
-pub fn to_path_str(key: &str) -> PathKey {
+pub fn to_path_str(key: &str) -> PathKey<'_> {
     let mut path_key = PathKey {
         key,
         special_key: None
@@ -47,8 +222,128 @@ pub fn to_path_str(key: &str) -> PathKey {
         let s = &key[1..key.len() - 1];
         path_key.key = s;
         if key.contains('\\') {
-            path_key.special_key = Some(s.chars().filter(|ch| ch != &'\\').collect());
+            path_key.special_key = Some(unescape(s));
         }
     }
     path_key
-}
\ No newline at end of file
+}
+
+// the form of a member name that `key_match` compares
+pub fn fold_key(key: &str, key_match: KeyMatch) -> Cow<'_, str> {
+    match key_match {
+        KeyMatch::Exact => Cow::Borrowed(key),
+        KeyMatch::AsciiCaseInsensitive => Cow::Owned(key.to_ascii_lowercase()),
+        KeyMatch::CaseFold => Cow::Owned(caseless::default_case_fold_str(key)),
+        KeyMatch::Nfc if is_nfc(key) => Cow::Borrowed(key),
+        KeyMatch::Nfc => Cow::Owned(key.nfc().collect()),
+    }
+}
+
+/// The RFC 9535 normalized path of each of `nodes`, e.g. `$['store']['book'][0]`.
+pub fn to_normalized_paths(origin: &Value, nodes: &[&Value]) -> Vec<String> {
+    let targets: HashSet<*const Value> = nodes.iter().map(|v| *v as *const Value).collect();
+    let mut paths = HashMap::new();
+    let mut path = String::from("$");
+    walk_normalized_paths(origin, &targets, &mut path, &mut paths);
+
+    nodes.iter()
+        .filter_map(|v| paths.get(&(*v as *const Value)).cloned())
+        .collect()
+}
+
+fn walk_normalized_paths(v: &Value,
+                         targets: &HashSet<*const Value>,
+                         path: &mut String,
+                         paths: &mut HashMap<*const Value, String>) {
+    if targets.contains(&(v as *const Value)) {
+        paths.insert(v as *const Value, path.clone());
+    }
+
+    let len = path.len();
+    match v {
+        Value::Array(vec) => {
+            for (i, v) in vec.iter().enumerate() {
+                let _ = write!(path, "[{}]", i);
+                walk_normalized_paths(v, targets, path, paths);
+                path.truncate(len);
+            }
+        }
+        Value::Object(map) => {
+            for (k, v) in map {
+                push_normalized_name(path, k);
+                walk_normalized_paths(v, targets, path, paths);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The parent and the member name or array index of each of `nodes`, e.g. `"book"` or `0`.
+/// The root of `origin` has neither and is `None`.
+pub fn to_members<'a>(origin: &'a Value, nodes: &[&'a Value]) -> Vec<Option<(&'a Value, Value)>> {
+    let targets: HashSet<*const Value> = nodes.iter().map(|v| *v as *const Value).collect();
+    let mut members = HashMap::new();
+    walk_members(origin, &targets, &mut members);
+
+    nodes.iter()
+        .map(|v| members.get(&(*v as *const Value)).cloned())
+        .collect()
+}
+
+fn walk_members<'a>(v: &'a Value,
+                    targets: &HashSet<*const Value>,
+                    members: &mut HashMap<*const Value, (&'a Value, Value)>) {
+    let mut visit = |name: Value, child: &'a Value| {
+        if targets.contains(&(child as *const Value)) {
+            members.insert(child as *const Value, (v, name));
+        }
+        walk_members(child, targets, members);
+    };
+
+    match v {
+        Value::Array(vec) => vec.iter().enumerate().for_each(|(i, child)| visit(Value::from(i), child)),
+        Value::Object(map) => map.iter().for_each(|(k, child)| visit(Value::String(k.clone()), child)),
+        _ => {}
+    }
+}
+
+/// The member name or array index of each of `nodes`, leaving out the root of `origin`.
+pub fn to_member_names(origin: &Value, nodes: &[&Value]) -> Vec<Value> {
+    to_members(origin, nodes).into_iter()
+        .flatten()
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// The member name of the parent of each of `nodes`; `None` for the root and its children.
+pub fn to_parent_names(origin: &Value, nodes: &[&Value]) -> Vec<Option<Value>> {
+    let members = to_members(origin, nodes);
+    let parents: Vec<&Value> = members.iter().flatten().map(|(parent, _)| *parent).collect();
+    let mut parent_members = to_members(origin, &parents).into_iter();
+
+    members.iter()
+        .map(|member| member.as_ref().and_then(|_| parent_members.next().flatten()).map(|(_, name)| name))
+        .collect()
+}
+
+// RFC 9535 2.7: names are single quoted with the minimal escaping
+fn push_normalized_name(path: &mut String, name: &str) {
+    path.push_str("['");
+    for ch in name.chars() {
+        match ch {
+            '\u{8}' => path.push_str("\\b"),
+            '\u{c}' => path.push_str("\\f"),
+            '\n' => path.push_str("\\n"),
+            '\r' => path.push_str("\\r"),
+            '\t' => path.push_str("\\t"),
+            '\'' => path.push_str("\\'"),
+            '\\' => path.push_str("\\\\"),
+            c if c < ' ' => {
+                let _ = write!(path, "\\u{:04x}", c as u32);
+            }
+            c => path.push(c),
+        }
+    }
+    path.push_str("']");
+}
@@ -1,9 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use regex::Regex;
+use serde_json::Map;
 use serde_json::Value;
+use super::selector_impl::{DescentOrder, KeyMatch};
 use super::utils;
 use selector::utils::PathKey;
 
+// how far and in which order `..` walks
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct Descent {
+    pub depth: Option<usize>,
+    pub order: DescentOrder,
+}
+
+// the parent of each node a path has stepped to, by the address of the node
+pub(super) type Parents<'a> = HashMap<*const Value, &'a Value>;
+
 pub(super) struct ValueWalker;
 
 impl<'a> ValueWalker {
@@ -18,10 +31,10 @@ impl<'a> ValueWalker {
         })
     }
 
-    pub fn next_with_str(vec: &[&'a Value], key: &'a str) -> Vec<&'a Value> {
+    pub fn next_with_num(vec: &[&'a Value], index: f64) -> Vec<&'a Value> {
         vec.iter().fold(Vec::new(), |mut acc, v| {
-            if let Value::Object(map) = v {
-                if let Some(v) = map.get(key) {
+            if let Value::Array(vec) = v {
+                if let Some(v) = vec.get(utils::abs_index(index as isize, vec.len())) {
                     acc.push(v);
                 }
             }
@@ -29,10 +42,10 @@ impl<'a> ValueWalker {
         })
     }
 
-    pub fn next_with_num(vec: &[&'a Value], index: f64) -> Vec<&'a Value> {
+    pub fn next_with_index(vec: &[&'a Value], index: isize) -> Vec<&'a Value> {
         vec.iter().fold(Vec::new(), |mut acc, v| {
             if let Value::Array(vec) = v {
-                if let Some(v) = vec.get(utils::abs_index(index as isize, vec.len())) {
+                if let Some(v) = utils::normalize_index(index, vec.len()).and_then(|i| vec.get(i)) {
                     acc.push(v);
                 }
             }
@@ -40,39 +53,96 @@ impl<'a> ValueWalker {
         })
     }
 
-    pub fn all_with_num(vec: &[&'a Value], index: f64) -> Vec<&'a Value> {
-        Self::walk(vec, &|v, acc| {
-            if v.is_array() {
-                if let Some(v) = v.get(index as usize) {
-                    acc.push(v);
-                }
+    pub fn next_with_range(vec: &[&'a Value], from: Option<isize>, to: Option<isize>, step: Option<isize>) -> Vec<&'a Value> {
+        vec.iter().fold(Vec::new(), |mut acc, v| {
+            if let Value::Array(vec) = v {
+                acc.extend(utils::slice_indices(from, to, step, vec.len()).into_iter().map(|i| &vec[i]));
             }
+            acc
         })
     }
 
-    pub fn all_with_str(vec: &[&'a Value], key: &'a str) -> Vec<&'a Value> {
-        let path_key = utils::to_path_str(key);
-        Self::walk(vec, &|v, acc| if let Value::Object(map) = v {
-            if let Some(v) = map.get(path_key.get_key()) {
-                acc.push(v);
+    pub fn next_with_key_pattern(vec: &[&'a Value], regex: &Regex) -> Vec<&'a Value> {
+        vec.iter().fold(Vec::new(), |mut acc, v| {
+            if let Value::Object(map) = v {
+                acc.extend(map.iter().filter(|(k, _)| regex.is_match(k)).map(|(_, v)| v));
+            }
+            acc
+        })
+    }
+
+    // records `vec` as the parents of their children, and of all their descendants with `is_descendant`
+    pub fn track_parents(vec: &[&'a Value], is_descendant: bool, parents: &mut Parents<'a>) {
+        for parent in vec {
+            let children = Self::next_all(&[*parent]);
+            for child in &children {
+                parents.insert(*child as *const Value, parent);
+            }
+            if is_descendant {
+                Self::track_parents(&children, true, parents);
+            }
+        }
+    }
+
+    // elements of arrays and any other value itself, each node once
+    pub fn next_dedup(vec: &[&'a Value]) -> Vec<&'a Value> {
+        let mut visited = HashSet::new();
+        vec.iter().fold(Vec::new(), |mut acc, v| {
+            match v {
+                Value::Array(vec) => acc.extend(vec.iter().filter(|v| visited.insert(*v as *const Value))),
+                _ => if visited.insert(*v as *const Value) {
+                    acc.push(v)
+                },
             }
+            acc
         })
     }
 
-    pub fn all_with_strs(vec: &[&'a Value], keys: &[&'a str]) -> Vec<&'a Value> {
+    // elements of arrays, members of objects and any other value itself, each node once
+    pub fn next_members_dedup(vec: &[&'a Value]) -> Vec<&'a Value> {
+        let mut visited = HashSet::new();
+        vec.iter().fold(Vec::new(), |mut acc, v| {
+            match v {
+                Value::Array(vec) => acc.extend(vec.iter().filter(|v| visited.insert(*v as *const Value))),
+                Value::Object(map) => acc.extend(map.values().filter(|v| visited.insert(*v as *const Value))),
+                _ => if visited.insert(*v as *const Value) {
+                    acc.push(v)
+                },
+            }
+            acc
+        })
+    }
+
+    pub fn all_with_str(vec: &[&'a Value], key: &'a str, descent: Descent, key_match: KeyMatch) -> Vec<&'a Value> {
+        let path_key = utils::to_path_str(key);
+        Self::walk(vec, descent, &|v, acc| if let Value::Object(map) = v {
+            Self::members_with_key(map, path_key.get_key(), key_match, acc);
+        })
+    }
+
+    pub fn all_with_strs(vec: &[&'a Value], keys: &[&'a str], key_match: KeyMatch) -> Vec<&'a Value> {
         let path_keys: &Vec<PathKey> = &keys.iter().map(|key| { utils::to_path_str(key) }).collect();
         vec.iter().fold(Vec::new(), |mut acc, v| {
             if let Value::Object(map) = v {
-                path_keys.iter().for_each(|pk| if let Some(v) = map.get(pk.get_key()) {
-                    acc.push(v)
-                });
+                path_keys.iter().for_each(|pk| Self::members_with_key(map, pk.get_key(), key_match, &mut acc));
             }
             acc
         })
     }
 
-    pub fn all(vec: &[&'a Value]) -> Vec<&'a Value> {
-        Self::walk(vec, &|v, acc| {
+    // the members named `key`, in document order when more than one matches
+    fn members_with_key(map: &'a Map<String, Value>, key: &str, key_match: KeyMatch, acc: &mut Vec<&'a Value>) {
+        if key_match == KeyMatch::Exact {
+            acc.extend(map.get(key));
+            return;
+        }
+
+        let key = utils::fold_key(key, key_match);
+        acc.extend(map.iter().filter(|(k, _)| utils::fold_key(k, key_match) == key).map(|(_, v)| v));
+    }
+
+    pub fn all(vec: &[&'a Value], descent: Descent) -> Vec<&'a Value> {
+        Self::walk(vec, descent, &|v, acc| {
             match v {
                 Value::Array(ay) => acc.extend(ay),
                 Value::Object(map) => {
@@ -83,94 +153,55 @@ impl<'a> ValueWalker {
         })
     }
 
-    fn walk<F>(vec: &[&'a Value], fun: &F) -> Vec<&'a Value>
+    pub fn self_and_all(vec: &[&'a Value], descent: Descent) -> Vec<&'a Value> {
+        Self::walk(vec, descent, &|v, acc| acc.push(v))
+    }
+
+    // `fun` collects the children of a node, so it is not called on the nodes `depth` levels down
+    fn walk<F>(vec: &[&'a Value], descent: Descent, fun: &F) -> Vec<&'a Value>
         where
             F: Fn(&'a Value, &mut Vec<&'a Value>),
     {
         vec.iter().fold(Vec::new(), |mut acc, v| {
-            Self::_walk(v, &mut acc, fun);
+            match descent.order {
+                DescentOrder::PreOrder => Self::_walk(v, &mut acc, descent.depth, fun),
+                DescentOrder::BreadthFirst => Self::walk_breadth_first(v, &mut acc, descent.depth, fun),
+            }
             acc
         })
     }
 
-    fn _walk<F>(v: &'a Value, acc: &mut Vec<&'a Value>, fun: &F)
+    fn _walk<F>(v: &'a Value, acc: &mut Vec<&'a Value>, depth: Option<usize>, fun: &F)
         where
             F: Fn(&'a Value, &mut Vec<&'a Value>),
     {
+        if depth == Some(0) {
+            return;
+        }
+
         fun(v, acc);
 
+        let depth = depth.map(|depth| depth - 1);
         match v {
             Value::Array(vec) => {
-                vec.iter().for_each(|v| Self::_walk(v, acc, fun));
+                vec.iter().for_each(|v| Self::_walk(v, acc, depth, fun));
             }
             Value::Object(map) => {
-                map.values().into_iter().for_each(|v| Self::_walk(v, acc, fun));
+                map.values().for_each(|v| Self::_walk(v, acc, depth, fun));
             }
             _ => {}
         }
     }
 
-    pub fn walk_dedup_all<F1, F2>(vec: &[&'a Value],
-                                  key: &str,
-                                  visited: &mut HashSet<*const Value>,
-                                  is_contain: &mut F1,
-                                  is_not_contain: &mut F2,
-                                  depth: usize)
-        where
-            F1: FnMut(&'a Value),
-            F2: FnMut(usize),
-    {
-        vec.iter().enumerate().for_each(|(index, v)| Self::walk_dedup(v,
-                                                                      key,
-                                                                      visited,
-                                                                      index,
-                                                                      is_contain,
-                                                                      is_not_contain,
-                                                                      depth));
-    }
-
-    fn walk_dedup<F1, F2>(v: &'a Value,
-                          key: &str,
-                          visited: &mut HashSet<*const Value>,
-                          index: usize,
-                          is_contain: &mut F1,
-                          is_not_contain: &mut F2,
-                          depth: usize)
+    fn walk_breadth_first<F>(v: &'a Value, acc: &mut Vec<&'a Value>, mut depth: Option<usize>, fun: &F)
         where
-            F1: FnMut(&'a Value),
-            F2: FnMut(usize),
+            F: Fn(&'a Value, &mut Vec<&'a Value>),
     {
-        let ptr = v as *const Value;
-        if visited.contains(&ptr) {
-            return;
-        }
-
-        match v {
-            Value::Object(map) => {
-                if map.get(key).is_some() {
-                    let ptr = v as *const Value;
-                    if !visited.contains(&ptr) {
-                        visited.insert(ptr);
-                        is_contain(v);
-                    }
-                } else if depth == 0 {
-                    is_not_contain(index);
-                }
-            }
-            Value::Array(vec) => {
-                if depth == 0 {
-                    is_not_contain(index);
-                }
-                vec.iter().for_each(|v| {
-                    Self::walk_dedup(v, key, visited, index, is_contain, is_not_contain, depth + 1);
-                })
-            }
-            _ => {
-                if depth == 0 {
-                    is_not_contain(index);
-                }
-            }
+        let mut level = vec![v];
+        while !level.is_empty() && depth != Some(0) {
+            level.iter().for_each(|v| fun(v, acc));
+            level = Self::next_all(&level);
+            depth = depth.map(|depth| depth - 1);
         }
     }
 }
-
@@ -107,6 +107,20 @@ fn array_range_step_by_2() {
     );
 }
 
+#[test]
+fn array_range_negative_step() {
+    setup();
+
+    let json = json!(["first", "second", "third", "forth", "fifth"]);
+
+    select_and_then_compare("$[::-1]", json.clone(), json!(["fifth", "forth", "third", "second", "first"]));
+    select_and_then_compare("$[4:0:-2]", json.clone(), json!(["fifth", "third"]));
+    select_and_then_compare("$[-1:-3:-1]", json.clone(), json!(["fifth", "forth"]));
+    select_and_then_compare("$[:2:-1]", json.clone(), json!(["fifth", "forth"]));
+    select_and_then_compare("$[10:-10:-3]", json.clone(), json!(["fifth", "second"]));
+    select_and_then_compare("$[0:3:-1]", json, json!([]));
+}
+
 #[test]
 fn array_range_only_negative_index() {
     setup();
@@ -217,6 +231,44 @@ fn array_multiple_key() {
     );
 }
 
+#[test]
+fn array_mixed_union() {
+    setup();
+
+    let json = json!({
+        "name": "list",
+        "items": [{"x": 1}, {"y": 2}, {"x": 3}, 4, 5]
+    });
+
+    select_and_then_compare(
+        "$.items[0, 2:4, ?(@.x)]",
+        json.clone(),
+        json!([{"x": 1}, {"x": 3}, 4, {"x": 1}, {"x": 3}]),
+    );
+    select_and_then_compare(
+        "$['name', 'items'][0, 'name']",
+        json.clone(),
+        json!([{"x": 1}]),
+    );
+    select_and_then_compare(
+        "$.items[-1, *]",
+        json.clone(),
+        json!([5, {"x": 1}, {"y": 2}, {"x": 3}, 4, 5]),
+    );
+
+    // the selectors are applied to each node in turn
+    select_and_then_compare(
+        "$.items[1, 0]['x', 'y']",
+        json.clone(),
+        json!([2, 1]),
+    );
+    select_and_then_compare(
+        "$.items[0:3]['y', 'x']",
+        json,
+        json!([1, 2, 3]),
+    );
+}
+
 #[test]
 fn bugs40_bracket_notation_after_recursive_descent() {
     setup();
@@ -6,7 +6,7 @@ use std::io::Read;
 
 use serde_json::Value;
 
-use self::jsonpath::{JsonSelector, PathParser};
+use self::jsonpath::{JsonSelector, ParserMode, PathParser};
 
 #[allow(dead_code)]
 pub fn setup() {
@@ -45,6 +45,22 @@ pub fn select_and_then_compare(path: &str, json: Value, target: Value) {
     );
 }
 
+#[allow(dead_code)]
+pub fn select_rfc9535_and_then_compare(path: &str, json: Value, target: Value) {
+    let parser = PathParser::compile_with_mode(path, ParserMode::Rfc9535).unwrap();
+    let mut selector = JsonSelector::new(parser);
+    let result = selector.value(&json).select_as::<Value>().unwrap();
+    assert_eq!(
+        result,
+        match target {
+            Value::Array(vec) => vec,
+            _ => panic!("Give me the Array!"),
+        },
+        "{}",
+        path
+    );
+}
+
 #[allow(dead_code)]
 pub fn compare_result(result: Vec<&Value>, target: Value) {
     let result = serde_json::to_value(result).unwrap();
@@ -21,6 +21,22 @@ fn quote() {
     );
 }
 
+#[test]
+fn quote_escapes() {
+    setup();
+
+    let json = json!({"aA": 1, "line\nbreak": 2, "back\\slash": 3, "😀": 4, "a/b": 5, "end\\": 6});
+
+    select_and_then_compare(r#"$['\u0061\u0041']"#, json.clone(), json!([1]));
+    select_and_then_compare(r#"$["line\nbreak"]"#, json.clone(), json!([2]));
+    select_and_then_compare(r#"$['back\\slash']"#, json.clone(), json!([3]));
+    select_and_then_compare(r#"$['\uD83D\uDE00', 'a\/b']"#, json.clone(), json!([4, 5]));
+    select_and_then_compare(r#"$['end\\']"#, json.clone(), json!([6]));
+    select_and_then_compare(r#"$[?(@['line\nbreak'] == 2)].aA"#, json.clone(), json!([1]));
+    select_and_then_compare(r#"$[?(@.aA == 1)]['back\\slash']"#, json, json!([3]));
+    select_and_then_compare(r#"$[?(@ == 'a\u0041')]"#, json!(["aA", "b"]), json!(["aA"]));
+}
+
 #[test]
 fn filter_next_all() {
     setup();
@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate serde_json;
 
-use common::{read_json, select_and_then_compare, setup};
+use common::{read_json, select_and_then_compare, select_rfc9535_and_then_compare, setup};
 
 mod common;
 
@@ -43,8 +43,8 @@ fn op_object_or_default() {
         "$.friends[?(@.id >= 2 || @.id == 1)]",
         read_json("./benchmark/data_obj.json"),
         json!([
-            { "id" : 2, "name" : "Gray Berry" },
-            { "id" : 1, "name" : "Vincent Cannon" }
+            { "id" : 1, "name" : "Vincent Cannon" },
+            { "id" : 2, "name" : "Gray Berry" }
         ]),
     );
 }
@@ -60,6 +60,230 @@ fn op_object_and_or() {
     );
 }
 
+#[test]
+fn op_not() {
+    setup();
+
+    select_and_then_compare(
+        "$..book[?(!@.isbn)].title",
+        read_json("./benchmark/example.json"),
+        json!(["Sayings of the Century", "Sword of Honour"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(!(@.price < 10 || @.category == 'reference'))].price",
+        read_json("./benchmark/example.json"),
+        json!([12.99, 22.99]),
+    );
+
+    select_and_then_compare(
+        "$.school.friends[?(@.id == 1 || !(@.id >= 1) && @.name)].id",
+        read_json("./benchmark/data_obj.json"),
+        json!([0, 1]),
+    );
+
+    select_and_then_compare(
+        "$.friends[?(!@.name)].id",
+        read_json("./benchmark/data_obj.json"),
+        json!([0]),
+    );
+}
+
+#[test]
+fn op_arithmetic() {
+    setup();
+
+    select_and_then_compare(
+        "$..book[?(@.price * 2 > 40)].price",
+        read_json("./benchmark/example.json"),
+        json!([22.99]),
+    );
+
+    let json = json!([
+        {"id": 0, "price": 12, "qty": 10, "start": 0, "end": 3600},
+        {"id": 1, "price": 2.5, "qty": 4, "start": 60, "end": 120},
+        {"id": 2, "price": "12", "qty": 10},
+        {"id": 3, "price": 9007199254740993_u64, "qty": 0}
+    ]);
+
+    select_and_then_compare("$[?(@.price * @.qty > 100)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.end - @.start >= 3600)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.price*@.qty == 10)].id", json.clone(), json!([1]));
+
+    // `*`, `/` and `%` first, then left to right
+    select_and_then_compare("$[?(@.price + 1 * 2 == 4.5)].id", json.clone(), json!([1]));
+    select_and_then_compare("$[?((@.price + 1) * 2 == 7)].id", json.clone(), json!([1]));
+    select_and_then_compare("$[?(@.qty - 4 - 3 == 3)].id", json.clone(), json!([0, 2]));
+    select_and_then_compare("$[?(@.qty == 20 / 4 * 2)].id", json.clone(), json!([0, 2]));
+    select_and_then_compare("$[?(@.end % 7 == 1)].id", json.clone(), json!([1]));
+    select_and_then_compare("$[?(@.price / 2 == 6)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.qty / 8 == 1.25)].id", json.clone(), json!([0, 2]));
+
+    // only numbers are operands, and a division by zero has no value
+    select_and_then_compare("$[?(@.price + 0 == 12)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.price / @.qty >= 0)].id", json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?(@.price % @.qty == 2)].id", json.clone(), json!([0]));
+
+    // integers stay exact
+    select_and_then_compare("$[?(@.price - 1 > 9007199254740991)].id", json.clone(), json!([3]));
+
+    // an integer result beyond i64 falls back to f64
+    let min = json!({"a": [{"b": i64::MIN}]});
+    select_and_then_compare("$.a[?(@.b / -1 > 0)].b", min.clone(), json!([i64::MIN]));
+    select_and_then_compare("$.a[?(@.b % -1 == 0)].b", min.clone(), json!([i64::MIN]));
+    select_and_then_compare("$.a[?(@.b * -1 > 0)].b", min, json!([i64::MIN]));
+
+    select_and_then_compare("$[?(@.qty == $[1].qty * 2 + 2)].id", json, json!([0, 2]));
+}
+
+#[test]
+fn op_null() {
+    setup();
+
+    let json = json!([
+        {"id": 0, "deletedAt": null, "parent": 3},
+        {"id": 1, "deletedAt": "2020-01-01", "parent": null},
+        {"id": 2},
+        {"id": 3, "deletedAt": false}
+    ]);
+
+    // a missing member is neither null nor anything else
+    select_and_then_compare("$[?(@.deletedAt == null)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(null == @.deletedAt)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.deletedAt != null)].id", json.clone(), json!([1, 3]));
+    select_and_then_compare("$[?(@.parent != null)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(!@.deletedAt)].id", json.clone(), json!([2]));
+    select_and_then_compare("$[?(@.deletedAt >= null)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.deletedAt < null)].id", json.clone(), json!([]));
+    select_and_then_compare("$[?(@.parent in [null, 3])].id", json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?(@.parent == $[0].deletedAt)].id", json, json!([1]));
+}
+
+#[test]
+fn op_json_literal() {
+    setup();
+
+    let json = json!([
+        {"id": 0, "dims": [10, 20], "owner": {"id": 1, "name": "a"}},
+        {"id": 1, "dims": [10.0, 20], "owner": {"name": "a", "id": 1.0}},
+        {"id": 2, "dims": [20, 10], "owner": {"id": 1}},
+        {"id": 3}
+    ]);
+
+    select_and_then_compare("$[?(@.dims == [10, 20])].id", json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?([10, 20] == @.dims)].id", json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?(@.dims != [10, 20])].id", json.clone(), json!([2]));
+    select_and_then_compare(r#"$[?(@.owner == {"id": 1})].id"#, json.clone(), json!([2]));
+    // member order does not matter
+    select_and_then_compare(r#"$[?(@.owner == {'name': 'a', "id": 1})].id"#, json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?(@.owner in [{'id': 1}, []])].id", json.clone(), json!([2]));
+    // arrays and objects are not ordered
+    select_and_then_compare("$[?(@.dims > [1])].id", json.clone(), json!([]));
+    select_and_then_compare("$[?(@.owner != {} && @.dims != [20, 10])].id", json, json!([0, 1]));
+    // a brace in a member name must be quoted
+    select_and_then_compare("$['p{q}']", json!({"p{q}": 3}), json!([3]));
+}
+
+#[test]
+fn op_property_names() {
+    setup();
+
+    let json = json!({"items": [{"id": 0, "isbn": "a"}, {"id": 1}, {"id": 2, "issn": "b"}]});
+
+    select_and_then_compare("$.items[?(@.*~ == 'isbn')].id", json.clone(), json!([0]));
+    select_and_then_compare("$.items[?('id' in @.*~)].id", json.clone(), json!([0, 1, 2]));
+    select_and_then_compare("$.items[?(@.*~ =~ /^is/)].id", json.clone(), json!([0, 2]));
+    select_and_then_compare("$.items[?(@.*~ != 'isbn')].id", json.clone(), json!([1, 2]));
+    select_and_then_compare("$.items[?(@.id == 1 || $.*~ == 'none')].id", json, json!([1]));
+}
+
+#[test]
+fn op_number_literals() {
+    setup();
+
+    let json = json!([
+        {"id": 0, "a": 1000000},
+        {"id": 1, "a": -1.5},
+        {"id": 2, "a": 0.0015},
+        {"id": 3, "a": 9007199254740993_u64},
+        {"id": 4, "a": -0.0025}
+    ]);
+
+    select_and_then_compare("$[?(@.a == 1e6)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.a == 1E+6)].id", json.clone(), json!([0]));
+    select_and_then_compare("$[?(@.a < -1.4)].id", json.clone(), json!([1]));
+    select_and_then_compare("$[?(@.a == 1.5e-3)].id", json.clone(), json!([2]));
+    select_and_then_compare("$[?(@.a == -2.5E-3)].id", json.clone(), json!([4]));
+    select_and_then_compare("$[?(@.a in [-1.5, 1e6])].id", json.clone(), json!([0, 1]));
+    select_and_then_compare("$[?(@.a == 9007199254740993)].id", json.clone(), json!([3]));
+    select_and_then_compare("$[1e0].id", json, json!([1]));
+}
+
+#[test]
+fn op_exact_integers() {
+    setup();
+
+    let json = json!({
+        "target": 9007199254740993_u64,
+        "records": [
+            {"id": 9007199254740992_u64},
+            {"id": 9007199254740993_u64},
+            {"id": 18446744073709551615_u64},
+            {"id": 1.5}
+        ]
+    });
+
+    select_and_then_compare("$.records[?(@.id == 9007199254740993)].id", json.clone(), json!([9007199254740993_u64]));
+    select_and_then_compare("$.records[?(@.id == $.target)].id", json.clone(), json!([9007199254740993_u64]));
+    select_and_then_compare(
+        "$.records[?(@.id > 9007199254740992)].id",
+        json.clone(),
+        json!([9007199254740993_u64, 18446744073709551615_u64]),
+    );
+    select_and_then_compare("$.records[?(@.id < 2)].id", json.clone(), json!([1.5]));
+    select_and_then_compare("$.records[?(@.id >= 1.5)].id", json.clone(), json!([
+        9007199254740992_u64, 9007199254740993_u64, 18446744073709551615_u64, 1.5
+    ]));
+    select_rfc9535_and_then_compare("$.records[?@.id == $.target].id", json, json!([9007199254740993_u64]));
+}
+
+#[cfg(feature = "arbitrary_precision")]
+#[test]
+fn op_arbitrary_precision() {
+    setup();
+
+    let json: serde_json::Value = serde_json::from_str(r#"[
+        {"id": 0, "n": 123456789012345678901234567890},
+        {"id": 1, "n": 123456789012345678901234567891},
+        {"id": 2, "n": 1.00000000000000000001}
+    ]"#).unwrap();
+
+    select_and_then_compare("$[?(@.n == 123456789012345678901234567891)].id", json.clone(), json!([1]));
+    select_and_then_compare("$[?(@.n < 123456789012345678901234567891)].id", json.clone(), json!([0, 2]));
+    select_and_then_compare("$[?(@.n > 1)].id", json, json!([0, 1, 2]));
+}
+
+#[test]
+fn op_context_variables() {
+    setup();
+
+    let json = json!({
+        "max": 25,
+        "users": {"admin": {"age": 50}, "bob": {"age": 20}, "eve": {"age": 30}},
+        "groups": {"x": [{"id": 1}], "y": [{"id": 2}, {"id": 3}]}
+    });
+
+    select_and_then_compare("$.users[?(@property != 'admin')].age", json.clone(), json!([20, 30]));
+    select_and_then_compare("$.users.*[?(@.age < @root.max)].age", json.clone(), json!([20]));
+    select_and_then_compare("$.groups.y[?(@property > 0)].id", json.clone(), json!([3]));
+    select_and_then_compare("$..[?(@parentProperty == 'y')].id", json.clone(), json!([2, 3]));
+    select_and_then_compare("$.groups.*[?(@path == \"$['groups']['x'][0]\")].id", json.clone(), json!([1]));
+    // a filter over an object selects among its members
+    select_and_then_compare("$.users[?(@property != 'admin')]", json.clone(), json!([{"age": 20}, {"age": 30}]));
+    select_and_then_compare("$.users[?(@property == 'users')]", json.clone(), json!([]));
+    select_and_then_compare("$[?(@path == \"$['max']\")]", json, json!([25]));
+}
+
 #[test]
 fn op_result_type() {
     setup();
@@ -332,6 +556,68 @@ fn cmp_json_rel() {
     )
 }
 
+#[test]
+fn op_path_value_equality() {
+    setup();
+
+    let json = json!({
+        "home": {"city": "Seoul", "zip": 1},
+        "wanted": ["a", "b"],
+        "orders": [
+            {
+                "id": 0,
+                "billing": {"address": {"city": "Seoul", "zip": 1}},
+                "shipping": {"address": {"zip": 1.0, "city": "Seoul"}},
+                "tags": ["b", "c"],
+                "min": 1,
+                "max": 2
+            },
+            {
+                "id": 1,
+                "billing": {"address": {"city": "Seoul"}},
+                "shipping": {"address": {"city": "Busan"}},
+                "tags": ["c"],
+                "min": 3,
+                "max": 2
+            },
+            {"id": 2, "billing": {"address": {"city": "Seoul"}}, "tags": []}
+        ]
+    });
+
+    select_and_then_compare(
+        "$.orders[?(@.billing.address == @.shipping.address)].id",
+        json.clone(),
+        json!([0]),
+    );
+    // a missing operand is neither equal nor unequal
+    select_and_then_compare(
+        "$.orders[?(@.billing.address != @.shipping.address)].id",
+        json.clone(),
+        json!([1]),
+    );
+    select_and_then_compare("$.orders[?(@.shipping.address == $.home)].id", json.clone(), json!([0]));
+    select_and_then_compare("$.orders[?($.home == @.billing.address)].id", json.clone(), json!([0]));
+    // several nodes: `==` if some pair is equal, `!=` if none is
+    select_and_then_compare("$.orders[?(@.tags[*] == $.wanted[*])].id", json.clone(), json!([0]));
+    select_and_then_compare("$.orders[?(@.tags[*] != $.wanted[*])].id", json.clone(), json!([1]));
+    select_and_then_compare("$.orders[?(@.min < @.max)].id", json.clone(), json!([0]));
+    select_and_then_compare("$.orders[?(@.max <= @.min)].id", json, json!([1]));
+}
+
+#[test]
+fn op_ne_for_relative_paths() {
+    setup();
+
+    let json = json!({"a": [{"x": 1, "z": 2}, {"x": 3, "z": 3}, {"x": 4}]});
+
+    select_and_then_compare("$.a[?(@.x != @.z)]", json.clone(), json!([{"x": 1, "z": 2}]));
+
+    // RFC 9535 selects no candidate when none holds, where the default mode falls back to the array
+    select_rfc9535_and_then_compare("$.a[?@.x != @.x]", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$.a[?@.p != @.q]", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$.a[?@.p != 1]", json, json!([{"x": 1, "z": 2}, {"x": 3, "z": 3}, {"x": 4}]));
+}
+
 #[test]
 fn op_lt_for_object_value() {
     setup();
@@ -461,4 +747,149 @@ fn op_for_same_type() {
             {"a": 1}
         ]),
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn op_regex_match() {
+    setup();
+
+    select_and_then_compare(
+        "$..book[?(@.isbn =~ /^0-553/i)].title",
+        read_json("./benchmark/example.json"),
+        json!(["Moby Dick"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(@.author=~/rees|TOLKIEN/i)].author",
+        read_json("./benchmark/example.json"),
+        json!(["Nigel Rees", "J. R. R. Tolkien"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(@.author =~ /Rees|TOLKIEN/)].author",
+        read_json("./benchmark/example.json"),
+        json!(["Nigel Rees"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(@.category =~ /^fic/ && @.price < 10)].title",
+        read_json("./benchmark/example.json"),
+        json!(["Moby Dick"]),
+    );
+}
+
+#[test]
+fn op_regex_flags_and_escapes() {
+    setup();
+
+    select_and_then_compare(
+        r#"$[?(@.a =~ /^x\/y$/)]"#,
+        json!([{"a": "x/y"}, {"a": "x\\/y"}]),
+        json!([{"a": "x/y"}]),
+    );
+
+    select_and_then_compare(
+        "$[?(@ =~ /^b$/m)]",
+        json!(["a\nb", "ab"]),
+        json!(["a\nb"]),
+    );
+
+    select_and_then_compare(
+        "$[?(@ =~ /a.b/s)]",
+        json!(["a\nb", "a b", "ab"]),
+        json!(["a\nb", "a b"]),
+    );
+
+    select_and_then_compare(
+        "$[?(@ =~ /1/)]",
+        json!([1, "1", ["1"], {"a": "1"}]),
+        json!(["1"]),
+    );
+}
+
+#[test]
+fn op_regex_linear_time() {
+    setup();
+
+    let text = ["a".repeat(10_000), "!".to_string()].concat();
+    select_and_then_compare(
+        "$[?(@ =~ /^(a+)+$/)]",
+        json!([text]),
+        json!([]),
+    );
+}
+
+#[test]
+fn op_in_and_nin() {
+    setup();
+
+    select_and_then_compare(
+        "$..book[?(@.category in ['fiction','poetry'])].title",
+        read_json("./benchmark/example.json"),
+        json!(["Sword of Honour", "Moby Dick", "The Lord of the Rings"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(@.price in [8.95, 22.99])].title",
+        read_json("./benchmark/example.json"),
+        json!(["Sayings of the Century", "The Lord of the Rings"]),
+    );
+
+    select_and_then_compare(
+        "$..book[?(@.category nin ['fiction'])].title",
+        read_json("./benchmark/example.json"),
+        json!(["Sayings of the Century"]),
+    );
+
+    // a missing member is in no set, and not in one either
+    select_and_then_compare(
+        "$..book[?(@.isbn nin ['0-553-21311-3'])].title",
+        read_json("./benchmark/example.json"),
+        json!(["The Lord of the Rings"]),
+    );
+
+    select_and_then_compare(
+        "$.items[?(@.id in $.wanted)].id",
+        json!({"wanted": [1, [2]], "items": [{"id": 1}, {"id": [2]}, {"id": 3}]}),
+        json!([1, [2]]),
+    );
+}
+
+#[test]
+fn op_subsetof_anyof_noneof() {
+    setup();
+
+    let json = json!({
+        "wanted": ["a", "b"],
+        "items": [
+            {"tags": ["a", "c"]},
+            {"tags": ["c"]},
+            {"tags": ["b", "a"]},
+            {"tags": "a"}
+        ]
+    });
+
+    select_and_then_compare(
+        "$.items[?(@.tags subsetof $.wanted)]",
+        json.clone(),
+        json!([{"tags": ["b", "a"]}]),
+    );
+
+    select_and_then_compare(
+        "$.items[?(@.tags anyof $.wanted)]",
+        json.clone(),
+        json!([{"tags": ["a", "c"]}, {"tags": ["b", "a"]}]),
+    );
+
+    select_and_then_compare(
+        "$.items[?(@.tags noneof $.wanted)]",
+        json.clone(),
+        json!([{"tags": ["c"]}]),
+    );
+
+    select_and_then_compare(
+        "$.items[?(@.tags subsetof ['a', 'b', 'c'] && 'c' in @.tags)]",
+        json,
+        json!([{"tags": ["a", "c"]}, {"tags": ["c"]}]),
+    );
+}
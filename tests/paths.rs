@@ -112,4 +112,65 @@ fn dolla_token_in_path() {
            "Match 2.3.6.1"
         ]),
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn key_pattern() {
+    setup();
+
+    let json = json!({
+        "stats": {"metric_cpu": 1, "metric_mem": 2, "other": 3, "a*b": 4, "axb": 5},
+        "nested": {"metric_io": 6}
+    });
+
+    select_and_then_compare("$.stats[~'metric_*']", json.clone(), json!([1, 2]));
+    select_and_then_compare("$.stats[/^metric_/]", json.clone(), json!([1, 2]));
+    select_and_then_compare("$.stats[/CPU|MEM/i]", json.clone(), json!([1, 2]));
+    select_and_then_compare("$..[~'metric_*']", json.clone(), json!([1, 2, 6]));
+    select_and_then_compare("$..[/^metric_/]", json.clone(), json!([1, 2, 6]));
+    select_and_then_compare("$.stats[~'a*b']", json.clone(), json!([4, 5]));
+    select_and_then_compare(r#"$.stats[~'a\*b']"#, json.clone(), json!([4]));
+    select_and_then_compare("$.stats[~'metric_*', 'other']", json.clone(), json!([1, 2, 3]));
+
+    // without `~` a quoted name is always an exact name
+    select_and_then_compare("$.stats['a*b']", json.clone(), json!([4]));
+    select_and_then_compare("$.stats['metric_*']", json.clone(), json!([]));
+    select_and_then_compare("$..['metric_*', 'other']", json, json!([3]));
+
+    // a quoted operand in a filter is a string, not a pattern
+    select_and_then_compare(
+        "$[?(@.a == 'x*')]",
+        json!([{"a": "x*"}, {"a": "xy"}]),
+        json!([{"a": "x*"}]),
+    );
+}
+
+#[test]
+fn parent() {
+    setup();
+
+    let json = json!({"orders": [
+        {"id": 1, "items": [{"price": 1500}, {"price": 2000}]},
+        {"id": 2, "items": [{"price": 10}]},
+        {"id": 3, "items": [{"price": 1001}]}
+    ]});
+
+    select_and_then_compare("$..items[?(@.price > 1000)]^^.id", json.clone(), json!([1, 3]));
+    select_and_then_compare(
+        "$..items[?(@.price > 1000)]^",
+        json.clone(),
+        json!([[{"price": 1500}, {"price": 2000}], [{"price": 1001}]]),
+    );
+    select_and_then_compare("$.orders[0]^[2].id", json.clone(), json!([3]));
+    select_and_then_compare("$..price^^^.id", json.clone(), json!([1, 2, 3]));
+    select_and_then_compare("$.orders[?(@.items[0]^^.id == 2)].id", json.clone(), json!([2]));
+    select_and_then_compare("$.orders[0, 2].items[0]^^.id", json.clone(), json!([1, 3]));
+    select_and_then_compare("$.orders[0, 'x', 2].id^.id", json.clone(), json!([1, 3]));
+    select_and_then_compare("$.orders[?($.orders[1].items^.id == @.id)].id", json.clone(), json!([2]));
+    select_and_then_compare("$^", json, json!([]));
+
+    // `^` ends a dot-notation name, so a name with `^` in it must be quoted
+    let json = json!({"a^b": 1, "a": {"b": 2}});
+    select_and_then_compare("$['a^b']", json.clone(), json!([1]));
+    select_and_then_compare("$.a.b^.b", json, json!([2]));
+}
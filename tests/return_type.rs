@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate serde_json;
 
-use common::{read_json, select_and_then_compare, setup};
+use common::{read_json, select_and_then_compare, select_rfc9535_and_then_compare, setup};
 
 mod common;
 
@@ -96,6 +96,22 @@ fn return_type_for_array_filter_true() {
     );
 }
 
+#[test]
+fn return_type_for_array_filter_absolute_true() {
+    setup();
+
+    select_rfc9535_and_then_compare(
+        r#"$.a[?$.k == "v"]"#,
+        json!({"k": "v", "a": [1, {"b": 2}]}),
+        json!([1, {"b": 2}]),
+    );
+    select_rfc9535_and_then_compare(
+        r#"$.a[?$.k == "w"]"#,
+        json!({"k": "v", "a": [1, {"b": 2}]}),
+        json!([]),
+    );
+}
+
 #[test]
 fn return_type_empty() {
     setup();
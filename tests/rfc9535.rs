@@ -0,0 +1,379 @@
+#[macro_use]
+extern crate serde_json;
+extern crate jsonpath_lib as jsonpath;
+
+use common::{read_json, select_and_then_compare, select_rfc9535_and_then_compare, setup};
+use std::rc::Rc;
+
+use serde_json::Value;
+
+use jsonpath::{FunctionArgument, FunctionRegistry, FunctionType, JsonSelector, ParserMode, PathParser};
+
+mod common;
+
+fn is_valid(path: &str) -> bool {
+    PathParser::compile_with_mode(path, ParserMode::Rfc9535).is_ok()
+}
+
+fn select_paths(path: &str, json: &serde_json::Value) -> Vec<String> {
+    let parser = PathParser::compile_with_mode(path, ParserMode::Rfc9535).unwrap();
+    JsonSelector::new(parser).value(json).select_paths().unwrap()
+}
+
+#[test]
+fn rfc9535_grammar() {
+    setup();
+
+    for path in &[
+        "$",
+        "$.a",
+        "$.a_b.µ.韓창",
+        "$.prix_€.가격",
+        "$['a'].b[0][-1]",
+        "$[0, 1]",
+        "$[1:2:3]",
+        "$[5:0:-2]",
+        "$..*",
+        "$..['a']",
+        "$.a[?@.b]",
+        "$.a[?(@.b == 1)]",
+        "$.a[?@.b == 1 && @.c || @.d == 2]",
+        "$[?$.a]",
+        "$[?@.a == $.b[0]]",
+        "$ .a [0]",
+        "$[?@ .a == 1]",
+        "$[?@.a == 'a', 'b']",
+        "$['a', 0, 1:2, *, ?@.b]",
+        r#"$['\u0041\uD83D\uDE00\n\/\\\'']"#,
+        r#"$["\"'"]"#,
+        "$[?@.a == -1.5e-3 || @.a == 1E+6 || @.a == -0]",
+    ] {
+        assert!(is_valid(path), "{}", path);
+    }
+
+    for path in &[
+        " $",
+        "$ ",
+        "$.a ",
+        "$.1a",
+        "$.a-b",
+        "$.['a']",
+        "$a",
+        "$[01]",
+        "$[-0]",
+        "$[::-0]",
+        "$[9007199254740992]",
+        "$[0]]",
+        "$[?@.a == True]",
+        "$[?@.a == 01]",
+        "$[?@.a[*] == 1]",
+        "$[?@..a == 1]",
+        r#"$['\"']"#,
+        r#"$["\'"]"#,
+        r#"$['\x']"#,
+        r#"$['\uD83D']"#,
+        "$['\t']",
+        "$[?@.a == 1.]",
+        "$[?@.a == 1e]",
+    ] {
+        assert!(!is_valid(path), "{}", path);
+    }
+
+    // the default grammar is unchanged
+    assert!(PathParser::compile("$.['a']").is_ok());
+    assert!(PathParser::compile("$.a-b").is_ok());
+    assert!(PathParser::compile("$[?($.a)]").is_err());
+}
+
+#[test]
+fn rfc9535_store_examples() {
+    setup();
+
+    let json = read_json("./benchmark/example.json");
+
+    select_rfc9535_and_then_compare(
+        "$.store.book[*].author",
+        json.clone(),
+        json!(["Nigel Rees", "Evelyn Waugh", "Herman Melville", "J. R. R. Tolkien"]),
+    );
+    select_rfc9535_and_then_compare(
+        "$..book[2].author",
+        json.clone(),
+        json!(["Herman Melville"]),
+    );
+    select_rfc9535_and_then_compare(
+        "$..book[-1].title",
+        json.clone(),
+        json!(["The Lord of the Rings"]),
+    );
+    select_rfc9535_and_then_compare(
+        "$..book[?@.isbn].title",
+        json.clone(),
+        json!(["Moby Dick", "The Lord of the Rings"]),
+    );
+    select_rfc9535_and_then_compare(
+        "$..book[?@.price<10].title",
+        json.clone(),
+        json!(["Sayings of the Century", "Moby Dick"]),
+    );
+    select_rfc9535_and_then_compare(
+        "$.store.book[?@.price < $.expensive].price",
+        json,
+        json!([8.95, 8.99]),
+    );
+}
+
+#[test]
+fn rfc9535_duplicates_and_order() {
+    setup();
+
+    let json = json!({"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]});
+
+    select_rfc9535_and_then_compare("$.a[0, 0, 1]", json.clone(), json!([5, 5, 3]));
+    select_rfc9535_and_then_compare("$..j", json.clone(), json!([1, 4]));
+    select_rfc9535_and_then_compare("$..[0]", json.clone(), json!([5, {"j": 4}]));
+    select_rfc9535_and_then_compare("$.a[-4]", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$.a[::0]", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$.a[::-1]", json.clone(), json!([[{"j": 4}, {"k": 6}], 3, 5]));
+    select_rfc9535_and_then_compare("$.a[-2::-2]", json.clone(), json!([3]));
+    select_rfc9535_and_then_compare("$.a[2, 0:2, ?@ == 3]", json.clone(), json!([[{"j": 4}, {"k": 6}], 5, 3, 3]));
+    select_rfc9535_and_then_compare("$..['j', 0]", json.clone(), json!([1, 5, {"j": 4}, 4]));
+
+    // a filter keeps the document order, whatever the order of its operands
+    let json = json!([{"id": 1}, {"id": 2}, {"id": 3}]);
+    select_rfc9535_and_then_compare("$[?@.id >= 3 || @.id == 1]", json.clone(), json!([{"id": 1}, {"id": 3}]));
+    select_and_then_compare("$[?(@.id >= 3 || @.id == 1)]", json, json!([{"id": 1}, {"id": 3}]));
+}
+
+#[test]
+fn rfc9535_filter_candidates() {
+    setup();
+
+    // an object's members are filtered, not the object itself
+    let json = json!({"a": {"b": 1}, "c": {"b": 2}});
+    select_rfc9535_and_then_compare("$[?@.b == 1]", json.clone(), json!([{"b": 1}]));
+    select_and_then_compare("$[?(@.b == 1)]", json, json!([]));
+
+    let json = json!([1, 2, 3]);
+    select_rfc9535_and_then_compare("$[?true == true]", json.clone(), json!([1, 2, 3]));
+    select_rfc9535_and_then_compare("$[?@ > 1]", json.clone(), json!([2, 3]));
+    select_rfc9535_and_then_compare("$[?$[0]]", json.clone(), json!([1, 2, 3]));
+    select_rfc9535_and_then_compare("$[?$[5]]", json, json!([]));
+}
+
+#[test]
+fn rfc9535_comparison() {
+    setup();
+
+    let json = json!([
+        {"a": 1},
+        {"a": 1.0},
+        {"a": "1"},
+        {"a": [1, {"b": 2}]},
+        {"a": [1, {"b": 2.0}]},
+        {"b": 1},
+        {"a": {"x": 1}, "b": {"x": 1}}
+    ]);
+
+    select_rfc9535_and_then_compare("$[?@.a == 1]", json.clone(), json!([{"a": 1}, {"a": 1.0}]));
+    select_rfc9535_and_then_compare("$[?@.a == '1']", json.clone(), json!([{"a": "1"}]));
+    select_rfc9535_and_then_compare(
+        "$[?@.a == $[3].a]",
+        json.clone(),
+        json!([{"a": [1, {"b": 2}]}, {"a": [1, {"b": 2.0}]}]),
+    );
+    select_rfc9535_and_then_compare(
+        "$[?@.a == @.b]",
+        json.clone(),
+        json!([{"a": {"x": 1}, "b": {"x": 1}}]),
+    );
+    // a missing member only equals another missing one
+    select_rfc9535_and_then_compare("$[?@.c == @.d]", json.clone(), json.clone());
+    select_rfc9535_and_then_compare("$[?@.a != 1]", json.clone(), json!([
+        {"a": "1"},
+        {"a": [1, {"b": 2}]},
+        {"a": [1, {"b": 2.0}]},
+        {"b": 1},
+        {"a": {"x": 1}, "b": {"x": 1}}
+    ]));
+    // only numbers and strings are ordered
+    select_rfc9535_and_then_compare("$[?@.a <= 1]", json.clone(), json!([{"a": 1}, {"a": 1.0}]));
+    select_rfc9535_and_then_compare("$[?@.a >= '1']", json.clone(), json!([{"a": "1"}]));
+    select_rfc9535_and_then_compare("$[?@.a < @.b]", json, json!([]));
+
+    // a null member is not a missing one
+    let json = json!([{"a": null}, {"a": 0}, {}]);
+    select_rfc9535_and_then_compare("$[?@.a == null]", json.clone(), json!([{"a": null}]));
+    select_rfc9535_and_then_compare("$[?@.a != null]", json.clone(), json!([{"a": 0}, {}]));
+    select_rfc9535_and_then_compare("$[?@.a <= null]", json, json!([{"a": null}]));
+}
+
+#[test]
+fn rfc9535_logical_precedence() {
+    setup();
+
+    let json = json!([
+        {"a": 1, "b": 1, "c": 0},
+        {"a": 0, "b": 0, "c": 1},
+        {"a": 1, "b": 0, "c": 0}
+    ]);
+
+    select_rfc9535_and_then_compare(
+        "$[?@.c == 1 || @.a == 1 && @.b == 1]",
+        json.clone(),
+        json!([{"a": 1, "b": 1, "c": 0}, {"a": 0, "b": 0, "c": 1}]),
+    );
+    select_rfc9535_and_then_compare(
+        "$[?(@.c == 1 || @.a == 1) && @.b == 1]",
+        json.clone(),
+        json!([{"a": 1, "b": 1, "c": 0}]),
+    );
+    select_rfc9535_and_then_compare(
+        "$[?!(@.c == 1 || @.a == 1) || !@.d && @.b == 1]",
+        json,
+        json!([{"a": 1, "b": 1, "c": 0}]),
+    );
+}
+
+#[test]
+fn rfc9535_normalized_paths() {
+    setup();
+
+    let json = json!({"a": [{"b": 1}, {"b": 2}], "k'\\\n": {"b": 3}});
+
+    assert_eq!(
+        select_paths("$..b", &json),
+        vec!["$['a'][0]['b']", "$['a'][1]['b']", "$['k\\'\\\\\\n']['b']"]
+    );
+    assert_eq!(select_paths("$.a[1, 1]", &json), vec!["$['a'][1]", "$['a'][1]"]);
+    assert_eq!(select_paths("$", &json), vec!["$"]);
+}
+
+#[test]
+fn rfc9535_function_types() {
+    setup();
+
+    for path in &[
+        "$[?length(@.a) > 1]",
+        "$[?count(@.*) == 0]",
+        "$[?count(@..a[?@.b]) == 0]",
+        "$[?match(@.a, 'a.*')]",
+        "$[?search(@.a, $.b) && value(@..c) == 1]",
+        "$[?length(value(@.*)) == 1]",
+        "$[?1 < length( @.a )]",
+    ] {
+        assert!(is_valid(path), "{}", path);
+    }
+
+    for path in &[
+        // a ValueType result is not a test, a LogicalType result is not comparable
+        "$[?length(@.a)]",
+        "$[?match(@.a, 'a') == true]",
+        // a ValueType parameter takes a singular query, a NodesType one a query
+        "$[?length(@..a) == 1]",
+        "$[?count(1) == 1]",
+        "$[?count(length(@)) == 1]",
+        // unknown functions, and the arguments must match the parameters
+        "$[?foo(@.a)]",
+        "$[?Length(@.a) == 1]",
+        "$[?length (@.a) == 1]",
+        "$[?length(@.a, 1) == 1]",
+        "$[?match(@.a) == 1]",
+    ] {
+        assert!(!is_valid(path), "{}", path);
+    }
+}
+
+#[test]
+fn rfc9535_functions() {
+    setup();
+
+    let json = json!([
+        {"a": "ab"},
+        {"a": "a\nb"},
+        {"a": "a.b"},
+        {"a": "^ab"},
+        {"a": [1, 2]},
+        {"a": {"x": 1}},
+        {"a": 1}
+    ]);
+
+    select_rfc9535_and_then_compare("$[?length(@.a) == 2]", json.clone(), json!([{"a": "ab"}, {"a": [1, 2]}]));
+    select_rfc9535_and_then_compare("$[?length(@.a) == 3].a", json.clone(), json!(["a\nb", "a.b", "^ab"]));
+    select_rfc9535_and_then_compare("$[?count(@..*) > 1]", json.clone(), json!([{"a": [1, 2]}, {"a": {"x": 1}}]));
+    select_rfc9535_and_then_compare("$[?value(@.a) == 'ab']", json.clone(), json!([{"a": "ab"}]));
+    select_rfc9535_and_then_compare("$[?value(@.*.x) == 1]", json.clone(), json!([{"a": {"x": 1}}]));
+
+    // I-Regexp: `.` matches no line end, `^` and `$` are ordinary characters
+    select_rfc9535_and_then_compare("$[?match(@.a, 'a.b')].a", json.clone(), json!(["a.b"]));
+    select_rfc9535_and_then_compare("$[?search(@.a, 'b$')].a", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$[?search(@.a, '^a')].a", json.clone(), json!(["^ab"]));
+    select_rfc9535_and_then_compare("$[?search(@.a, '[a-b]')].a", json.clone(), json!(["ab", "a\nb", "a.b", "^ab"]));
+    select_rfc9535_and_then_compare("$[?match(@.a, 'a[')]", json.clone(), json!([]));
+    select_rfc9535_and_then_compare("$[?match(@.a, $[0].a)].a", json, json!(["ab"]));
+
+    let json = read_json("./benchmark/example.json");
+    select_rfc9535_and_then_compare(
+        "$..book[?length(@.title) == length($.store.book[0].category)].title",
+        json.clone(),
+        json!(["Moby Dick"]),
+    );
+
+    // function results compare by the RFC 9535 rules in the default mode too
+    select_and_then_compare(
+        "$..book[?(count(@.isbn) == 0 && @.price < 10)].title",
+        json.clone(),
+        json!(["Sayings of the Century"]),
+    );
+    select_and_then_compare(
+        "$..book[?(search(@.author, 'R+ee'))].author",
+        json,
+        json!(["Nigel Rees"]),
+    );
+}
+
+#[test]
+fn rfc9535_function_registry() {
+    setup();
+
+    let mut functions = FunctionRegistry::new();
+    functions
+        .register_logical("is_valid_sku", &[FunctionType::Value], |args| match args[0] {
+            FunctionArgument::Value(Some(Value::String(s))) => s.starts_with("SKU-"),
+            _ => false,
+        }).unwrap()
+        .register_logical("within_days", &[FunctionType::Value, FunctionType::Value], |args| match (&args[0], &args[1]) {
+            (FunctionArgument::Value(Some(days)), FunctionArgument::Value(Some(limit))) => days.as_u64() <= limit.as_u64(),
+            _ => false,
+        }).unwrap()
+        .register_value("total", &[FunctionType::Nodes], |args| match &args[0] {
+            FunctionArgument::Nodes(nodes) => Some(nodes.iter().filter_map(|v| v.as_i64()).sum::<i64>().into()),
+            _ => None,
+        }).unwrap()
+        .register_logical("not", &[FunctionType::Logical], |args| args[0] == FunctionArgument::Logical(false)).unwrap();
+    assert!(functions.register_logical("match", &[], |_| true).is_err());
+    assert!(functions.register_logical("isValid", &[], |_| true).is_err());
+    let functions = Rc::new(functions);
+
+    let json = json!([
+        {"sku": "SKU-1", "days": 10, "qty": [1, 2]},
+        {"sku": "X-2", "days": 20, "qty": [5, 6]},
+        {"sku": "SKU-3", "days": 40, "qty": []}
+    ]);
+    let select = |path, mode| {
+        let parser = PathParser::compile_with_functions(path, mode, functions.clone()).unwrap();
+        let mut selector = JsonSelector::new(parser);
+        selector.value(&json).select_values().unwrap()
+    };
+
+    assert_eq!(select("$[?is_valid_sku(@.sku)].sku", ParserMode::Rfc9535), vec![json!("SKU-1"), json!("SKU-3")]);
+    assert_eq!(select("$[?within_days(@.days, 30) && is_valid_sku(@.sku)].sku", ParserMode::Rfc9535), vec![json!("SKU-1")]);
+    assert_eq!(select("$[?total(@.qty[*]) > 3].sku", ParserMode::Rfc9535), vec![json!("X-2")]);
+    assert_eq!(select("$[?not(@.qty[0])].sku", ParserMode::Rfc9535), vec![json!("SKU-3")]);
+    assert_eq!(select("$[?(is_valid_sku(@.sku) && @.days > 20)].sku", ParserMode::Default), vec![json!("SKU-3")]);
+
+    // the arguments are checked against the parameter types
+    assert!(PathParser::compile_with_functions("$[?is_valid_sku(@.*)]", ParserMode::Rfc9535, functions.clone()).is_err());
+    assert!(PathParser::compile_with_functions("$[?within_days(@.days)]", ParserMode::Rfc9535, functions.clone()).is_err());
+    assert!(PathParser::compile_with_functions("$[?total(@.qty)]", ParserMode::Rfc9535, functions).is_err());
+}
@@ -2,10 +2,12 @@ extern crate jsonpath_lib as jsonpath;
 #[macro_use]
 extern crate serde_json;
 
+use std::rc::Rc;
+
 use serde_json::Value;
 
 use common::{read_json, setup};
-use jsonpath::{PathParser, JsonSelector, JsonSelectorMut};
+use jsonpath::{DescentOrder, KeyMatch, PathParser, JsonSelector, JsonSelectorMut};
 
 mod common;
 
@@ -128,3 +130,225 @@ fn selector_remove() {
         ]
     );
 }
+
+#[test]
+fn selector_property_names() {
+    setup();
+
+    let json = read_json("./benchmark/example.json");
+
+    let parser = PathParser::compile("$.store.*~").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_values().unwrap(), vec![json!("book"), json!("bicycle")]);
+    assert!(selector.select().is_err());
+    assert_eq!(selector.select_as_str().unwrap(), r#"["book","bicycle"]"#);
+
+    let parser = PathParser::compile("$.store.book[?(@.isbn)]~").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_as::<usize>().unwrap(), vec![2, 3]);
+
+    let parser = PathParser::compile("$..author~").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_values().unwrap(), vec![json!("author"); 4]);
+
+    // the root has no name
+    let parser = PathParser::compile("$~").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_values().unwrap(), Vec::<Value>::new());
+
+    let parser = PathParser::compile("$.store.bicycle").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_values().unwrap(), vec![json!({"color": "red", "price": 19.95})]);
+
+    // nothing follows `~`, so a name with `~` in it must be quoted
+    assert!(PathParser::compile("$.store~.book").is_err());
+    assert!(PathParser::compile("$.x~y").is_err());
+    let json = json!({"x~y": 2});
+    let parser = PathParser::compile("$['x~y']").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert_eq!(selector.value(&json).select_values().unwrap(), vec![json!(2)]);
+}
+
+#[test]
+fn selector_max_descent_depth() {
+    setup();
+
+    let json = json!({"price": 1, "a": {"price": 2, "b": {"price": 3, "c": [{"price": 4}]}}});
+    let select = |path, depth| {
+        let parser = PathParser::compile(path).unwrap().with_max_descent_depth(depth);
+        let mut selector = JsonSelector::new(parser);
+        selector.value(&json).select().unwrap().into_iter().cloned().collect::<Vec<Value>>()
+    };
+
+    assert_eq!(select("$..price", 0), Vec::<Value>::new());
+    assert_eq!(select("$..price", 1), vec![json!(1)]);
+    assert_eq!(select("$..price", 3), vec![json!(1), json!(2), json!(3)]);
+    assert_eq!(select("$..price", 5), vec![json!(1), json!(2), json!(3), json!(4)]);
+    assert_eq!(select("$.a..price", 2), vec![json!(2), json!(3)]);
+    assert_eq!(select("$..['price']", 2), vec![json!(1), json!(2)]);
+    assert_eq!(select("$..*", 1), vec![json!(1), json["a"].clone()]);
+    assert_eq!(select("$..[?(@.price > 1)].price", 2), vec![json!(2), json!(3)]);
+
+    // a filter path descends under the same bound
+    assert_eq!(select("$[?(@..price == 3)].price", 2), Vec::<Value>::new());
+}
+
+#[test]
+fn selector_descent_order() {
+    setup();
+
+    let json = json!({
+        "x": {"y": {"name": "deep", "friends": [3]}},
+        "z": {"name": "shallow", "friends": [2]},
+        "friends": [1]
+    });
+    let select = |path, order| {
+        let parser = PathParser::compile(path).unwrap();
+        let mut selector = JsonSelector::new(parser);
+        selector.descent_order(order).value(&json).select().unwrap().into_iter().cloned().collect::<Vec<Value>>()
+    };
+
+    assert_eq!(select("$..name", DescentOrder::PreOrder), vec![json!("deep"), json!("shallow")]);
+    assert_eq!(select("$..name", DescentOrder::BreadthFirst), vec![json!("shallow"), json!("deep")]);
+    assert_eq!(select("$..friends[0]", DescentOrder::PreOrder), vec![json!(1), json!(3), json!(2)]);
+    assert_eq!(select("$..friends[0]", DescentOrder::BreadthFirst), vec![json!(1), json!(2), json!(3)]);
+    assert_eq!(select("$..[?(@.name)].name", DescentOrder::BreadthFirst), vec![json!("shallow"), json!("deep")]);
+    assert_eq!(select("$[?(@..name == 'deep')].z.name", DescentOrder::BreadthFirst), vec![json!("shallow")]);
+}
+
+#[test]
+fn selector_aggregates() {
+    setup();
+
+    let json = read_json("./benchmark/example.json");
+    let select = |path| {
+        let parser = PathParser::compile(path).unwrap();
+        let mut selector = JsonSelector::new(parser);
+        selector.value(&json).select_values().unwrap()
+    };
+
+    assert_eq!(select("$.store.book[*].price.sum()"), vec![json!(53.92)]);
+    assert_eq!(select("$..price.min()"), vec![json!(8.95)]);
+    assert_eq!(select("$..price.max()"), vec![json!(22.99)]);
+    assert_eq!(select("$.store.book[*].price.avg()"), vec![json!(13.48)]);
+    assert_eq!(select("$.store.book.length()"), vec![json!(4)]);
+    assert_eq!(select("$.store.book[*].length()"), vec![json!(4)]);
+    assert_eq!(select("$.store.keys()"), vec![json!(["book", "bicycle"])]);
+    assert_eq!(select("$.store.book[*].author.first()"), vec![json!("Nigel Rees")]);
+    assert_eq!(select("$.store.book[*].author.last()"), vec![json!("J. R. R. Tolkien")]);
+    assert_eq!(select("$.store.book[?(@.price > $..price.avg())].author"), vec![json!("J. R. R. Tolkien")]);
+
+    // numbers in a single array are aggregated, what is not a number is skipped
+    let json = json!({
+        "a": [1, 2, 3, 4, "5"],
+        "b": [],
+        "big": [9007199254740993_u64, 1],
+        "words": ["x", true, null, {"n": 1}],
+        "objects": [{"x": 1, "y": 2}, {"y": 3, "z": 4}, 5]
+    });
+    let select = |path| {
+        let parser = PathParser::compile(path).unwrap();
+        let mut selector = JsonSelector::new(parser);
+        selector.value(&json).select_values().unwrap()
+    };
+
+    assert_eq!(select("$.a.sum()"), vec![json!(10)]);
+    assert_eq!(select("$.a.stddev()"), vec![json!(1.118033988749895)]);
+    assert_eq!(select("$.a.length()"), vec![json!(5)]);
+    assert_eq!(select("$.a.last()"), vec![json!("5")]);
+    assert_eq!(select("$.big.sum()"), vec![json!(9007199254740994_u64)]);
+    assert_eq!(select("$.b.sum()"), Vec::<Value>::new());
+    assert_eq!(select("$.b.avg()"), Vec::<Value>::new());
+    assert_eq!(select("$.b.first()"), Vec::<Value>::new());
+
+    // with no number, or no object for `keys`, there is nothing to aggregate
+    assert_eq!(select("$.words.sum()"), Vec::<Value>::new());
+    assert_eq!(select("$.words.min()"), Vec::<Value>::new());
+    assert_eq!(select("$.words[0].sum()"), Vec::<Value>::new());
+    assert_eq!(select("$.a.keys()"), Vec::<Value>::new());
+    assert_eq!(select("$.words[0].keys()"), Vec::<Value>::new());
+
+    // several nodes are aggregated together
+    assert_eq!(select("$['a', 'big'][0].sum()"), vec![json!(9007199254740994_u64)]);
+    assert_eq!(select("$.objects[*].keys()"), vec![json!(["x", "y", "z"])]);
+    assert_eq!(select("$.objects.keys()"), vec![json!(["x", "y", "z"])]);
+    assert_eq!(select("$.objects[1].keys()"), vec![json!(["y", "z"])]);
+
+    let parser = PathParser::compile("$.a.max()").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    assert!(selector.value(&json).select().is_err());
+    assert_eq!(selector.select_as::<u64>().unwrap(), vec![4]);
+    assert_eq!(selector.select_as_str().unwrap(), "[4]");
+}
+
+#[test]
+fn selector_params() {
+    setup();
+
+    let json = json!({"users": [
+        {"name": "a", "age": 20, "role": "admin"},
+        {"name": "b", "age": 40, "role": "user"},
+        {"name": "c", "age": 50, "role": "admin"},
+        {"name": "d", "age": 60, "role": "' || true || '"}
+    ]});
+
+    let parser = Rc::new(PathParser::compile("$.users[?(@.age > $minAge && @.role == $role)].name").unwrap());
+    let mut selector = JsonSelector::new_ref(Rc::clone(&parser));
+    selector.value(&json).bind("minAge", json!(30)).bind("role", json!("admin"));
+    assert_eq!(selector.select().unwrap(), vec![&json!("c")]);
+    selector.reset_value().bind("minAge", json!(10));
+    assert_eq!(selector.select().unwrap(), vec![&json!("a"), &json!("c")]);
+
+    // a bound value is compared as it is, it can not change the path
+    let mut selector = JsonSelector::new_ref(Rc::clone(&parser));
+    selector.value(&json).params(vec![("minAge", json!(0)), ("role", json!("' || true || '"))]);
+    assert_eq!(selector.select().unwrap(), vec![&json!("d")]);
+
+    // every parameter must be bound
+    let mut selector = JsonSelector::new_ref(Rc::clone(&parser));
+    assert!(selector.value(&json).bind("minAge", json!(30)).select().is_err());
+
+    let parser = PathParser::compile("$.users[?(@.role in $roles)].name").unwrap();
+    let mut selector = JsonSelector::new(parser);
+    selector.value(&json).params(json!({"roles": ["user"]}).as_object().unwrap().clone());
+    assert_eq!(selector.select().unwrap(), vec![&json!("b")]);
+
+    let parser = PathParser::compile("$.users[?(@.role == $role)]").unwrap();
+    let mut selector_mut = JsonSelectorMut::new(parser);
+    let result = selector_mut.value(json.clone()).bind("role", json!("admin")).remove().unwrap().take().unwrap();
+    assert_eq!(result["users"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn selector_key_match() {
+    setup();
+
+    let json = json!({
+        "items": [{"Price": 1}, {"price": 2}, {"PRICE": 3, "price": 4}],
+        "Stra\u{df}e": 5,
+        "cafe\u{301}": 6
+    });
+
+    let select = |path: &str, key_match: KeyMatch| -> Vec<Value> {
+        let mut selector = JsonSelector::new(PathParser::compile(path).unwrap());
+        selector.value(&json).key_match(key_match).select().unwrap().into_iter().cloned().collect()
+    };
+
+    assert_eq!(select("$.items[*].price", KeyMatch::Exact), vec![json!(2), json!(4)]);
+    assert_eq!(select("$.items[*].price", KeyMatch::AsciiCaseInsensitive), vec![json!(1), json!(2), json!(3), json!(4)]);
+    assert_eq!(select("$..price", KeyMatch::AsciiCaseInsensitive), vec![json!(1), json!(2), json!(3), json!(4)]);
+    assert_eq!(select("$.items[?(@.PRICE)]", KeyMatch::Exact), vec![json!({"PRICE": 3, "price": 4})]);
+    assert_eq!(select("$.items[?(@.PRICE)]", KeyMatch::AsciiCaseInsensitive).len(), 3);
+    assert_eq!(select("$.items[?(@['price'] == 1)]", KeyMatch::CaseFold), vec![json!({"Price": 1})]);
+
+    assert!(select("$.STRASSE", KeyMatch::AsciiCaseInsensitive).is_empty());
+    assert_eq!(select("$.STRASSE", KeyMatch::CaseFold), vec![json!(5)]);
+
+    assert!(select("$['caf\u{e9}']", KeyMatch::CaseFold).is_empty());
+    assert_eq!(select("$['caf\u{e9}']", KeyMatch::Nfc), vec![json!(6)]);
+
+    let parser = PathParser::compile("$.items[*].price").unwrap();
+    let mut selector_mut = JsonSelectorMut::new(parser);
+    let result = selector_mut.value(json.clone()).key_match(KeyMatch::AsciiCaseInsensitive).remove().unwrap().take().unwrap();
+    assert_eq!(result["items"], json!([{}, {}, {}]));
+}